@@ -1,103 +1,156 @@
-extern crate getopts;
-extern crate glob;
-extern crate regex;
-
 extern crate ug;
 
-use ug::core;
+use ug::args::{Args, Mode, DEFAULT_MAX_DEPTH};
+use ug::complete;
+use ug::config;
 use ug::io;
+use ug::man;
+use ug::printer;
+use ug::search::{Event, Searcher};
+use ug::walk::{relative, Skipped};
 
+use std::env;
 use std::fs;
-use std::io::prelude::*;
+use std::io::IsTerminal;
 use std::process;
 
-use std::collections::LinkedList;
-
-use glob::glob;
-
-use std::fs::File;
-use std::path::{Path, PathBuf};
-
-use regex::Regex;
+/// something is being piped or redirected into ug, as opposed to stdin
+/// being a terminal or /dev/null
+#[cfg(unix)]
+fn stdin_is_readable() -> bool {
+    use std::os::unix::fs::FileTypeExt;
 
-use std::env;
-
-/// walk downwards from the current path and return
-/// a list of paths to files
-fn get_files(this_path: &Path, ignores: &[PathBuf]) -> Vec<PathBuf> {
-    let contents = fs::read_dir(this_path).unwrap();
-    let mut output: Vec<PathBuf> = Vec::new();
-
-    for path in contents {
-        let p = path.unwrap().path();
-        if ignores.contains(&p) {
-            continue;
-        }
-        if fs::metadata(&p).unwrap().is_dir() {
-            for child_path in get_files(&p, ignores) {
-                output.push(child_path)
-            }
-        } else if fs::metadata(&p).unwrap().is_file() {
-            output.push(p)
-        }
+    if std::io::stdin().is_terminal() {
+        return false;
     }
-
-    output
-}
-
-fn lines_of(file: &str) -> Vec<String> {
-    match File::open(file) {
-        Ok(mut f) => {
-            let mut buffer = String::new();
-            let _ = f.read_to_string(&mut buffer).unwrap();
-            buffer.lines().map(ToOwned::to_owned).collect()
+    match fs::metadata("/dev/stdin") {
+        Ok(m) => {
+            let t = m.file_type();
+            t.is_file() || t.is_fifo() || t.is_socket()
         }
-        Err(_) => Vec::new(),
+        Err(_) => false,
     }
 }
 
-fn get_ignored_files_from_config() -> LinkedList<PathBuf> {
-    let mut o = LinkedList::new();
-    for line in lines_of(".gitignore") {
-        o.push_back(Path::new(&line).to_path_buf())
-    }
-    o
+#[cfg(not(unix))]
+fn stdin_is_readable() -> bool {
+    !std::io::stdin().is_terminal()
 }
 
-fn get_things_you_should_ignore() -> Vec<PathBuf> {
-    let mut gitignored = get_ignored_files_from_config();
-
-    let known_files_to_ignore = glob(".git/*").unwrap().map(|x| x.unwrap());
-
-    gitignored.extend(known_files_to_ignore);
+/// print an error to stderr and quit
+fn fail(e: &str) -> ! {
+    eprintln!("ug: {}", e);
+    process::exit(1)
+}
 
-    // prefix files gathered with the search root
-    gitignored
-        .into_iter()
-        .map(|x| {
-            let mut new_path = PathBuf::from(".");
-            new_path.push(x.as_path());
-            new_path
-        }).collect()
+/// tell the user the search was cut short by the default depth limit, as
+/// opposed to one they asked for with --depth or -n
+fn depth_note(too_deep: bool, opts: &Args) {
+    if too_deep && opts.max_depth == Some(DEFAULT_MAX_DEPTH) {
+        eprintln!(
+            "ug: skipped directories nested deeper than {} levels, use --depth to go further",
+            DEFAULT_MAX_DEPTH
+        );
+    }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let (re, path, opts) = match io::get_opts(&args) {
-        Ok((pattern, path, o)) => (Regex::new(&pattern).unwrap(), path, o),
-        Err(_) => process::exit(1),
+    let (args, config_file) = config::args_with_config(&args);
+    let mut opts = match io::get_opts(&args) {
+        Ok(opts) => opts,
+        Err(e) => fail(&format!(
+            "{}\nTry '{} --help' for more information.",
+            e, args[0]
+        )),
     };
 
-    let files_to_ignore = get_things_you_should_ignore();
+    match opts.mode {
+        Mode::Help => {
+            print!("{}", io::usage(&args[0]));
+            return;
+        }
+        Mode::Version => {
+            println!("ug {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Mode::Completions(shell) => {
+            print!("{}", complete::script(shell));
+            return;
+        }
+        Mode::Man => {
+            print!("{}", man::page());
+            return;
+        }
+        Mode::PrintConfig => {
+            if let Some(p) = config_file {
+                println!("# config file: {}", p.display());
+            }
+            for a in args.iter().skip(1).filter(|a| *a != "--print-config") {
+                println!("{}", a);
+            }
+            return;
+        }
+        Mode::Search | Mode::ListFiles => {}
+    }
+    if opts.paths.is_empty() {
+        if opts.mode == Mode::Search && stdin_is_readable() {
+            opts.paths.push("-".to_string());
+        } else {
+            opts.paths.push(".".to_string());
+        }
+    }
 
-    let results: Vec<core::FileResult> = get_files(Path::new(&path), &files_to_ignore)
-        .into_iter()
-        .map(|p| {
-            let such_lines = core::matching_lines(&p, &re);
-            (p, such_lines)
-        }).collect();
+    let searcher = Searcher::from_args(&opts).unwrap_or_else(|e| fail(&e));
+
+    // directories past --depth get one note at the end rather than one each
+    let mut too_deep = false;
+    let mut message = |event: Event| match event {
+        _ if opts.no_messages => {}
+        Event::Error(p, e) => eprintln!("ug: {}: {}", p.display(), e),
+        Event::Skipped(Skipped::TooLarge(p, len)) => eprintln!(
+            "ug: skipping {}: {} bytes is over --max-filesize",
+            p.display(),
+            len
+        ),
+        Event::Skipped(Skipped::TooDeep(_)) => too_deep = true,
+        Event::Searched(_) => {}
+    };
 
-    for l in io::display_output(results, &opts) {
+    if opts.mode == Mode::ListFiles {
+        let files = searcher.files(&mut message).unwrap_or_else(|e| fail(&e));
+        for p in files {
+            let wanted = match opts.file_name_pattern {
+                Some(ref re) => re.is_match(&relative(&p).to_string_lossy()),
+                None => true,
+            };
+            if wanted {
+                println!("{}", p.display());
+            }
+        }
+        depth_note(too_deep, &opts);
+        return;
+    }
+
+    // files are printed in the order of the walk, so output is only as
+    // deterministic as the walk and --sort make it
+    let mut printer = printer::for_args(&opts);
+    let stats = searcher
+        .search(|event| match event {
+            Event::Searched(result) => {
+                for l in printer.file(&result) {
+                    println!("{}", l);
+                }
+            }
+            other => message(other),
+        })
+        .unwrap_or_else(|e| fail(&e));
+    for l in printer.finish() {
         println!("{}", l);
     }
+    depth_note(too_deep, &opts);
+
+    if opts.stats {
+        println!("{}", stats);
+    }
 }
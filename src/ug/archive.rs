@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use tar;
+use zip::ZipArchive;
+
+use decompress;
+
+/// Archive formats whose members can be searched one by one with `-z`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// a tarball, compressed or not
+    Tar,
+    Zip,
+}
+
+/// tell archives apart from other files by name, since a tarball's
+/// contents are only identifiable once it is decompressed
+pub fn kind(p: &Path) -> Option<Kind> {
+    let name = p.file_name()?.to_str()?.to_lowercase();
+    if name.ends_with(".zip") {
+        return Some(Kind::Zip);
+    }
+    // drop the compression suffix, if any, to look at what was compressed
+    let inner = match decompress::Format::from_extension(Path::new(&name)) {
+        Some(_) => Path::new(&name).file_stem()?.to_str()?.to_string(),
+        None => name.clone(),
+    };
+    let tarballs = [".tgz", ".tbz2", ".txz"];
+    if inner.ends_with(".tar") || tarballs.iter().any(|t| name.ends_with(t)) {
+        Some(Kind::Tar)
+    } else {
+        None
+    }
+}
+
+/// the path a member's matches are reported under, as in
+/// `archive.tar.gz:inner/path.rs`
+pub fn member_path(archive: &Path, member: &Path) -> PathBuf {
+    PathBuf::from(format!("{}:{}", archive.display(), member.display()))
+}
+
+/// call `visit` with the name and contents of each regular file inside the
+/// archive at `p`, in the order they are stored
+pub fn for_each_member<F>(p: &Path, visit: F) -> io::Result<()>
+where
+    F: FnMut(&Path, &mut dyn Read),
+{
+    match kind(p) {
+        Some(Kind::Tar) => tar_members(decompress::open(p)?, visit),
+        Some(Kind::Zip) => zip_members(File::open(p)?, visit),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a tar or zip archive",
+        )),
+    }
+}
+
+fn tar_members<R, F>(source: R, mut visit: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(&Path, &mut dyn Read),
+{
+    let mut archive = tar::Archive::new(source);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.into_owned();
+        visit(&name, &mut entry);
+    }
+    Ok(())
+}
+
+fn zip_members<R, F>(source: R, mut visit: F) -> io::Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&Path, &mut dyn Read),
+{
+    let mut archive = ZipArchive::new(source)?;
+    for i in 0..archive.len() {
+        let mut member = archive.by_index(i)?;
+        if !member.is_file() {
+            continue;
+        }
+        // names that would escape the archive are searched under their
+        // raw name rather than skipped
+        let name = match member.enclosed_name() {
+            Some(n) => n.to_path_buf(),
+            None => PathBuf::from(member.name()),
+        };
+        visit(&name, &mut member);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kind, member_path, tar_members, zip_members, Kind};
+    use std::io::{Cursor, Read, Write};
+    use std::path::{Path, PathBuf};
+    use tar;
+    use zip;
+
+    fn read_member(name: &Path, contents: &mut dyn Read) -> (PathBuf, String) {
+        let mut s = String::new();
+        contents.read_to_string(&mut s).unwrap();
+        (name.to_path_buf(), s)
+    }
+
+    #[test]
+    fn archive_kinds() {
+        assert_eq!(kind(Path::new("a.tar")), Some(Kind::Tar));
+        assert_eq!(kind(Path::new("a.tar.gz")), Some(Kind::Tar));
+        assert_eq!(kind(Path::new("a.TGZ")), Some(Kind::Tar));
+        assert_eq!(kind(Path::new("a.tar.zst")), Some(Kind::Tar));
+        assert_eq!(kind(Path::new("a.zip")), Some(Kind::Zip));
+        assert_eq!(kind(Path::new("a.log.gz")), None);
+        assert_eq!(kind(Path::new("tar")), None);
+    }
+
+    #[test]
+    fn member_paths_are_prefixed_by_the_archive() {
+        let p = member_path(Path::new("./logs.tar.gz"), Path::new("inner/path.rs"));
+        assert_eq!(p, PathBuf::from("./logs.tar.gz:inner/path.rs"));
+    }
+
+    #[test]
+    fn reads_tar_members() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "inner/a.txt", &b"hello"[..])
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut members = Vec::new();
+        tar_members(&bytes[..], |n, c| members.push(read_member(n, c))).unwrap();
+        assert_eq!(
+            members,
+            vec![(PathBuf::from("inner/a.txt"), "hello".to_string())]
+        );
+    }
+
+    #[test]
+    fn reads_zip_members() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("inner/a.txt", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(b"hello").unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut members = Vec::new();
+        zip_members(Cursor::new(&bytes), |n, c| members.push(read_member(n, c))).unwrap();
+        assert_eq!(
+            members,
+            vec![(PathBuf::from("inner/a.txt"), "hello".to_string())]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use getopts::Matches;
+use regex::Regex;
+
+use complete::Shell;
+use io::{self, DisplayMode};
+
+/// ag's default for how many directories deep to go
+pub const DEFAULT_MAX_DEPTH: usize = 25;
+
+/// what ug was asked to do
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mode {
+    /// search files for the pattern
+    #[default]
+    Search,
+    /// print the files that would be searched, `--files` and `-g`
+    ListFiles,
+    /// print the arguments once the config file is merged in
+    PrintConfig,
+    Help,
+    Version,
+    /// print a completion script for the shell
+    Completions(Shell),
+    /// print the man page
+    Man,
+}
+
+/// what `--sort` and `--sortr` order the files to search by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Path,
+    Modified,
+    Accessed,
+    Created,
+}
+
+/// The command line, checked and converted once so nothing past parsing
+/// has to look flags up by name.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub mode: Mode,
+    /// what to search for, only missing when not searching
+    pub pattern: Option<Regex>,
+    /// the paths given, empty when there were none
+    pub paths: Vec<String>,
+    pub display: DisplayMode,
+
+    /// `-G`, only search files whose paths match
+    pub file_search_regex: Option<Regex>,
+    /// `-g`, only list files whose paths match
+    pub file_name_pattern: Option<Regex>,
+    pub globs: Vec<String>,
+    pub ignores: Vec<String>,
+    pub ignore_dirs: Vec<String>,
+    pub hidden: bool,
+    pub max_filesize: Option<u64>,
+
+    /// `None` when the walk goes all the way down
+    pub max_depth: Option<usize>,
+    pub follow_links: bool,
+    pub one_device: bool,
+    pub sort: Option<SortBy>,
+    pub sort_reverse: bool,
+
+    pub search_zip: bool,
+    pub pre: Option<String>,
+    pub pre_globs: Vec<String>,
+
+    pub max_count: Option<usize>,
+    pub max_results: Option<usize>,
+    pub width: Option<usize>,
+    pub max_columns: Option<usize>,
+    pub stats: bool,
+    /// don't print errors or notes about skipped files to stderr
+    pub no_messages: bool,
+    pub no_config: bool,
+}
+
+impl Args {
+    /// check and convert everything getopts parsed
+    pub fn from_matches(matches: &Matches) -> Result<Args, String> {
+        if matches.opt_present("h") {
+            return Ok(Args {
+                mode: Mode::Help,
+                ..Args::default()
+            });
+        }
+        if matches.opt_present("V") {
+            return Ok(Args {
+                mode: Mode::Version,
+                ..Args::default()
+            });
+        }
+
+        if let Some(name) = matches.opt_str("generate-completions") {
+            let shell = Shell::from_name(&name)
+                .ok_or_else(|| format!("invalid shell '{}', expected bash, zsh or fish", name))?;
+            return Ok(Args {
+                mode: Mode::Completions(shell),
+                ..Args::default()
+            });
+        }
+
+        if let Some(kind) = matches.opt_str("generate") {
+            if kind != "man" {
+                return Err(format!("invalid --generate '{}', expected man", kind));
+            }
+            return Ok(Args {
+                mode: Mode::Man,
+                ..Args::default()
+            });
+        }
+
+        let mode = if matches.opt_present("print-config") {
+            Mode::PrintConfig
+        } else if matches.opt_present("files") || matches.opt_present("g") {
+            Mode::ListFiles
+        } else {
+            Mode::Search
+        };
+
+        // when not searching every free argument is a path
+        let (pattern, paths) = if mode == Mode::Search {
+            match matches.free.split_first() {
+                Some((pattern, paths)) => (Some(regex("pattern", pattern)?), paths),
+                None => return Err("no pattern given".to_string()),
+            }
+        } else {
+            (None, &matches.free[..])
+        };
+
+        let display = if matches.opt_present("l") {
+            DisplayMode::OnlyFiles
+        } else if matches.opt_present("c") {
+            DisplayMode::CountMatches
+        } else {
+            DisplayMode::Regular
+        };
+
+        let (sort, sort_reverse) = match (matches.opt_str("sort"), matches.opt_str("sortr")) {
+            (Some(_), Some(_)) => {
+                return Err("--sort and --sortr can't be used together".to_string())
+            }
+            (Some(by), None) => (Some(sort_by(&by)?), false),
+            (None, Some(by)) => (Some(sort_by(&by)?), true),
+            (None, None) => (None, false),
+        };
+
+        Ok(Args {
+            mode,
+            pattern,
+            paths: paths.to_vec(),
+            display,
+            file_search_regex: optional(matches, "G", |r| regex("-G", r))?,
+            file_name_pattern: optional(matches, "g", |r| regex("-g", r))?,
+            globs: matches.opt_strs("glob"),
+            ignores: matches.opt_strs("ignore"),
+            ignore_dirs: matches.opt_strs("ignore-dir"),
+            hidden: matches.opt_present("hidden"),
+            max_filesize: optional(matches, "max-filesize", io::parse_size)?,
+            max_depth: max_depth(matches)?,
+            follow_links: matches.opt_present("f"),
+            one_device: matches.opt_present("one-device"),
+            sort,
+            sort_reverse,
+            search_zip: matches.opt_present("z"),
+            pre: matches.opt_str("pre"),
+            pre_globs: matches.opt_strs("pre-glob"),
+            max_count: count(matches, "max-count")?,
+            max_results: count(matches, "max-results")?,
+            width: count(matches, "width")?,
+            max_columns: count(matches, "max-columns")?,
+            stats: matches.opt_present("stats"),
+            no_messages: matches.opt_present("no-messages"),
+            no_config: matches.opt_present("no-config"),
+        })
+    }
+}
+
+/// the option called `name` run through `parse`, if it was given
+fn optional<T, F>(matches: &Matches, name: &str, parse: F) -> Result<Option<T>, String>
+where
+    F: Fn(&str) -> Result<T, String>,
+{
+    match matches.opt_str(name) {
+        Some(s) => parse(&s).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn regex(what: &str, r: &str) -> Result<Regex, String> {
+    Regex::new(r).map_err(|e| format!("invalid {}: {}", what, e))
+}
+
+/// a non-negative number given to the long option called `name`
+fn count(matches: &Matches, name: &str) -> Result<Option<usize>, String> {
+    optional(matches, name, |n| {
+        n.parse()
+            .map_err(|_| format!("invalid --{} '{}', expected a number", name, n))
+    })
+}
+
+fn max_depth(matches: &Matches) -> Result<Option<usize>, String> {
+    if matches.opt_present("n") {
+        return Ok(Some(0));
+    }
+    let depth = matches
+        .opt_str("depth")
+        .or_else(|| matches.opt_str("max-depth"));
+    match depth {
+        None => Ok(Some(DEFAULT_MAX_DEPTH)),
+        Some(d) => match d.parse::<i64>() {
+            Ok(n) if n < 0 => Ok(None),
+            Ok(n) => Ok(Some(n as usize)),
+            Err(_) => Err(format!("invalid depth '{}', expected a number", d)),
+        },
+    }
+}
+
+fn sort_by(by: &str) -> Result<SortBy, String> {
+    match by {
+        "path" => Ok(SortBy::Path),
+        "modified" => Ok(SortBy::Modified),
+        "accessed" => Ok(SortBy::Accessed),
+        "created" => Ok(SortBy::Created),
+        _ => Err(format!(
+            "invalid sort '{}', expected path, modified, accessed or created",
+            by
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, Mode, SortBy, DEFAULT_MAX_DEPTH};
+    use complete::Shell;
+    use io::{get_opts, DisplayMode};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        let mut all = vec!["self".to_string()];
+        all.extend(args.iter().map(|a| a.to_string()));
+        get_opts(&all)
+    }
+
+    #[test]
+    fn test_search_args() {
+        let args = parse(&["beh", "src", "-c", "--sortr", "path", "-m", "3"]).unwrap();
+        assert_eq!(Mode::Search, args.mode);
+        assert_eq!("beh", args.pattern.unwrap().as_str());
+        assert_eq!(vec!["src".to_string()], args.paths);
+        assert_eq!(DisplayMode::CountMatches, args.display);
+        assert_eq!(Some(SortBy::Path), args.sort);
+        assert!(args.sort_reverse);
+        assert_eq!(Some(3), args.max_count);
+        assert_eq!(Some(DEFAULT_MAX_DEPTH), args.max_depth);
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(Some(2), parse(&["a", "--depth", "2"]).unwrap().max_depth);
+        assert_eq!(None, parse(&["a", "--max-depth", "-1"]).unwrap().max_depth);
+        assert_eq!(
+            Some(0),
+            parse(&["a", "-n", "--depth", "4"]).unwrap().max_depth
+        );
+    }
+
+    #[test]
+    fn test_help_and_version_need_no_pattern() {
+        assert_eq!(Mode::Help, parse(&["--help"]).unwrap().mode);
+        assert_eq!(Mode::Help, parse(&["-h", "--depth", "x"]).unwrap().mode);
+        assert_eq!(Mode::Version, parse(&["--version"]).unwrap().mode);
+        assert_eq!(
+            Mode::Completions(Shell::Zsh),
+            parse(&["--generate-completions", "zsh"]).unwrap().mode
+        );
+        assert_eq!(Mode::Man, parse(&["--generate", "man"]).unwrap().mode);
+    }
+
+    #[test]
+    fn test_bad_args_are_errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a", "--no-such-flag"]).is_err());
+        assert!(parse(&["a", "--depth"]).is_err());
+        assert!(parse(&["(", "src"]).is_err());
+        assert!(parse(&["a", "-m", "lots"]).is_err());
+        assert!(parse(&["a", "--sort", "size"]).is_err());
+        assert!(parse(&["a", "--sort", "path", "--sortr", "modified"]).is_err());
+        assert!(parse(&["a", "--max-filesize", "big"]).is_err());
+        assert!(parse(&["-g", "["]).is_err());
+        assert!(parse(&["--generate-completions", "tcsh"]).is_err());
+        assert!(parse(&["--generate", "html"]).is_err());
+    }
+}
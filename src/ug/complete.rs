@@ -0,0 +1,175 @@
+use io::{OptSpec, Takes, OPTIONS};
+
+/// a shell `--generate-completions` can write a script for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// the completion script for `shell`, covering every option in `OPTIONS`
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+/// the option's names as typed, short one first
+fn names(o: &OptSpec) -> Vec<String> {
+    let mut names = Vec::new();
+    if !o.short.is_empty() {
+        names.push(format!("-{}", o.short));
+    }
+    if !o.long.is_empty() {
+        names.push(format!("--{}", o.long));
+    }
+    names
+}
+
+fn bash() -> String {
+    let mut s = String::from(
+        "_ug() {\n    local cur prev\n    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    \
+         prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    case \"$prev\" in\n",
+    );
+    for o in OPTIONS.iter().filter(|o| o.takes != Takes::Nothing) {
+        let reply = if !o.values.is_empty() {
+            format!("$(compgen -W \"{}\" -- \"$cur\")", o.values.join(" "))
+        } else if o.hint == "COMMAND" {
+            "$(compgen -c -- \"$cur\")".to_string()
+        } else {
+            String::new()
+        };
+        s.push_str(&format!(
+            "        {})\n            COMPREPLY=({})\n            return\n            ;;\n",
+            names(o).join("|"),
+            reply
+        ));
+    }
+    let all: Vec<String> = OPTIONS.iter().flat_map(names).collect();
+    s.push_str(&format!(
+        "    esac\n    if [[ \"$cur\" == -* ]]; then\n        \
+         COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    fi\n}}\n\
+         complete -o default -F _ug ug\n",
+        all.join(" ")
+    ));
+    s
+}
+
+fn zsh() -> String {
+    let mut s = String::from("#compdef ug\n\n_arguments -s -S \\\n");
+    for o in OPTIONS {
+        let desc = o
+            .desc
+            .replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]");
+        // how each name takes its value, `-m3` or `-m 3`, `--depth=3` or `--depth 3`
+        let spelled: Vec<String> = names(o)
+            .into_iter()
+            .map(|n| match (o.takes, n.starts_with("--")) {
+                (Takes::Nothing, _) => n,
+                (_, true) => n + "=",
+                (_, false) => n + "+",
+            }).collect();
+        let action = if o.takes == Takes::Nothing {
+            String::new()
+        } else if !o.values.is_empty() {
+            format!(":{}:({})", o.hint, o.values.join(" "))
+        } else if o.hint == "COMMAND" {
+            format!(":{}:_command_names -e", o.hint)
+        } else {
+            format!(":{}: ", o.hint)
+        };
+        let line = if o.takes == Takes::Many {
+            format!("'*{}[{}]{}'", spelled[0], desc, action)
+        } else if spelled.len() > 1 {
+            format!(
+                "'({})'{{{}}}'[{}]{}'",
+                names(o).join(" "),
+                spelled.join(","),
+                desc,
+                action
+            )
+        } else {
+            format!("'{}[{}]{}'", spelled[0], desc, action)
+        };
+        s.push_str(&format!("    {} \\\n", line));
+    }
+    s.push_str("    '*:path:_files'\n");
+    s
+}
+
+fn fish() -> String {
+    let mut s = String::new();
+    for o in OPTIONS {
+        let mut line = "complete -c ug".to_string();
+        if !o.short.is_empty() {
+            line.push_str(&format!(" -s {}", o.short));
+        }
+        if !o.long.is_empty() {
+            line.push_str(&format!(" -l {}", o.long));
+        }
+        if o.takes != Takes::Nothing {
+            line.push_str(" -x");
+            if !o.values.is_empty() {
+                line.push_str(&format!(" -a '{}'", o.values.join(" ")));
+            } else if o.hint == "COMMAND" {
+                line.push_str(" -a '(__fish_complete_command)'");
+            }
+        }
+        let desc = o.desc.replace('\\', "\\\\").replace('\'', "\\'");
+        line.push_str(&format!(" -d '{}'\n", desc));
+        s.push_str(&line);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{script, Shell};
+    use io::OPTIONS;
+
+    #[test]
+    fn test_every_option_is_completed() {
+        for &shell in &[Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let s = script(shell);
+            for o in OPTIONS.iter().filter(|o| !o.long.is_empty()) {
+                let name = match shell {
+                    Shell::Fish => format!("-l {} ", o.long),
+                    _ => format!("--{}", o.long),
+                };
+                assert!(s.contains(&name), "{:?} is missing {}", shell, o.long);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_values_are_offered() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("--sort)\n            COMPREPLY=($(compgen -W \"path"));
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.contains("'--sortr=[Like --sort, but in reverse]:SORTBY:(path "));
+        let fish = script(Shell::Fish);
+        assert!(fish.contains("-l sort -x -a 'path modified accessed created'"));
+    }
+
+    #[test]
+    fn test_quotes_are_escaped() {
+        assert!(script(Shell::Zsh).contains("[Don'\\''t follow"));
+        assert!(script(Shell::Fish).contains("'Don\\'t follow"));
+    }
+}
@@ -0,0 +1,221 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use io::{OptSpec, Takes, OPTIONS};
+
+/// where the config file is looked for: `$UG_CONFIG_PATH`, then
+/// `$XDG_CONFIG_HOME/ug/config`, then `~/.config/ug/config`
+pub fn config_path() -> Option<PathBuf> {
+    let from_env = |name| env::var_os(name).filter(|v| !v.is_empty());
+    if let Some(p) = from_env("UG_CONFIG_PATH") {
+        return Some(PathBuf::from(p));
+    }
+    let config_home = match from_env("XDG_CONFIG_HOME") {
+        Some(p) => PathBuf::from(p),
+        None => PathBuf::from(from_env("HOME")?).join(".config"),
+    };
+    Some(config_home.join("ug").join("config"))
+}
+
+/// the arguments in a config file: one per line, so a flag and its value
+/// go on separate lines or are joined with `=`. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn parse(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// options that set the same thing, so giving any of them on the command
+/// line overrides the others in the config file
+const SAME_SETTING: &[&[&str]] = &[
+    &["list-files", "count"],
+    &["depth", "max-depth", "norecurse"],
+    &["sort", "sortr"],
+];
+
+/// what an option sets, the same for options in one `SAME_SETTING` group
+fn setting(o: &OptSpec) -> &'static str {
+    let name = if o.long.is_empty() { o.short } else { o.long };
+    match SAME_SETTING.iter().find(|names| names.contains(&name)) {
+        Some(names) => names[0],
+        None => name,
+    }
+}
+
+/// The arguments split up by the option each belongs to, so an option and
+/// its value stay together. Bunched up short flags like `-lc` are split
+/// apart, and anything that isn't a known option is grouped under `None`.
+fn group(args: &[String]) -> Vec<(Option<&'static OptSpec>, Vec<String>)> {
+    let takes_value = |o: Option<&OptSpec>| o.is_some_and(|o| o.takes != Takes::Nothing);
+    let mut groups = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            // everything after it is a free argument
+            let mut rest = vec![arg.clone()];
+            rest.extend(args.by_ref().cloned());
+            groups.push((None, rest));
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let name = long.split('=').next().unwrap_or(long);
+            let o = OPTIONS
+                .iter()
+                .find(|o| !o.long.is_empty() && o.long == name);
+            let mut group = vec![arg.clone()];
+            if takes_value(o) && !long.contains('=') {
+                group.extend(args.next().cloned());
+            }
+            groups.push((o, group));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            let letters = &arg[1..];
+            for (i, c) in letters.char_indices() {
+                let flag = format!("-{}", c);
+                let o = OPTIONS.iter().find(|o| o.short == &flag[1..]);
+                if !takes_value(o) {
+                    groups.push((o, vec![flag]));
+                    continue;
+                }
+                // the rest of the letters, or the next argument, is the value
+                let value = &letters[i + c.len_utf8()..];
+                let value = if value.is_empty() {
+                    args.next().cloned()
+                } else {
+                    Some(value.to_string())
+                };
+                groups.push((o, Some(flag).into_iter().chain(value).collect()));
+                break;
+            }
+        } else {
+            groups.push((None, vec![arg.clone()]));
+        }
+    }
+    groups
+}
+
+/// Put the arguments from the config file between the program name and
+/// the ones from the command line. Options that can only be given once are
+/// left out of the config's arguments when the command line sets the same
+/// thing, so the command line wins.
+pub fn merge(args: &[String], config: &[String]) -> Vec<String> {
+    let given: Vec<&str> = group(args.get(1..).unwrap_or_default())
+        .into_iter()
+        .filter_map(|(o, _)| o.map(setting))
+        .collect();
+    let mut merged = Vec::new();
+    merged.extend(args.first().cloned());
+    for (o, group) in group(config) {
+        let overridden = match o {
+            Some(o) => o.takes != Takes::Many && given.contains(&setting(o)),
+            None => false,
+        };
+        if !overridden {
+            merged.extend(group);
+        }
+    }
+    merged.extend(args.iter().skip(1).cloned());
+    merged
+}
+
+/// the config file's path and arguments, `None` when there is no file
+fn read_config() -> io::Result<Option<(PathBuf, Vec<String>)>> {
+    let p = match config_path() {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    match File::open(&p) {
+        Ok(mut f) => {
+            let mut contents = String::new();
+            f.read_to_string(&mut contents)?;
+            Ok(Some((p, parse(&contents))))
+        }
+        // not having a config file is fine unless one was asked for
+        Err(ref e)
+            if e.kind() == io::ErrorKind::NotFound && env::var_os("UG_CONFIG_PATH").is_none() =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// the command line with the config file's arguments merged in, along
+/// with the config file if one was read. With `--no-config` the file isn't
+/// even looked for.
+pub fn args_with_config(args: &[String]) -> (Vec<String>, Option<PathBuf>) {
+    if args.iter().any(|a| a == "--no-config") {
+        return (args.to_vec(), None);
+    }
+    match read_config() {
+        Ok(Some((p, config))) => (merge(args, &config), Some(p)),
+        Ok(None) => (args.to_vec(), None),
+        Err(e) => {
+            eprintln!("ug: could not read config file: {}", e);
+            (args.to_vec(), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{args_with_config, merge, parse};
+
+    fn strings(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_one_argument_per_line() {
+        let contents = "# always\n--hidden\n\n  --max-count=5\n--glob\n!*.min.js\n";
+        assert_eq!(
+            parse(contents),
+            strings(&["--hidden", "--max-count=5", "--glob", "!*.min.js"])
+        );
+    }
+
+    #[test]
+    fn config_goes_before_command_line() {
+        let args = strings(&["ug", "foo", "src"]);
+        let config = strings(&["--hidden"]);
+        assert_eq!(
+            merge(&args, &config),
+            strings(&["ug", "--hidden", "foo", "src"])
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let args = strings(&["ug", "-m", "1", "--hidden", "--sortr", "path", "foo"]);
+        let config = strings(&[
+            "--max-count=2",
+            "--hidden",
+            "--sort",
+            "modified",
+            "--glob",
+            "*.rs",
+        ]);
+        let mut expected = strings(&["ug", "--glob", "*.rs"]);
+        expected.extend(args[1..].iter().cloned());
+        assert_eq!(merge(&args, &config), expected);
+    }
+
+    #[test]
+    fn bunched_flags_are_overridden_one_by_one() {
+        let args = strings(&["ug", "-c", "foo"]);
+        let config = strings(&["-lzm3"]);
+        assert_eq!(
+            merge(&args, &config),
+            strings(&["ug", "-z", "-m", "3", "-c", "foo"])
+        );
+    }
+
+    #[test]
+    fn no_config_skips_config() {
+        let args = strings(&["ug", "--no-config", "foo"]);
+        assert_eq!(args_with_config(&args), (args, None));
+    }
+}
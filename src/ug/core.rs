@@ -1,35 +1,139 @@
 use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::str;
 
+use std::borrow::Cow;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use regex::Regex;
 
-pub type FileResult = (PathBuf, Vec<(usize, String)>);
-
-pub fn matching_lines(p: &PathBuf, pattern: &Regex) -> Vec<(usize, String)> {
-    use std::io::Read;
-    let mut buffer = String::new();
-    let mut f = File::open(&p).unwrap();
-    match f.read_to_string(&mut buffer) {
-        Ok(yay_read) => yay_read,
-        Err(_) => 0,
-    };
-    _matching_lines(&buffer, pattern)
+/// One line that matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// counting from 1
+    pub line_number: usize,
+    /// where the line starts in what was searched
+    pub byte_offset: usize,
+    /// the line, without its line ending
+    pub bytes: Vec<u8>,
+    /// where in the line the pattern matched, as byte offsets into `bytes`
+    pub ranges: Vec<Range<usize>>,
 }
 
-fn _matching_lines(contents: &str, pattern: &Regex) -> Vec<(usize, String)> {
-    contents
-        .lines()
-        .enumerate()
-        .filter(|&(_, x)| pattern.is_match(&x))
-        .map(|(i, x)| (i + 1, x.to_owned()))
-        .collect()
+impl Match {
+    /// the line as text, for printing
+    pub fn line(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+/// Everything that matched in one searched file, archive member or stdin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub matches: Vec<Match>,
+}
+
+/// search a file, failing if it can't be opened or read
+pub fn matching_lines(p: &PathBuf, pattern: &Regex) -> io::Result<Vec<Match>> {
+    matching_lines_in(File::open(p)?, pattern)
+}
+
+/// search anything readable, like stdin or a pipe
+pub fn matching_lines_in<R: Read>(source: R, pattern: &Regex) -> io::Result<Vec<Match>> {
+    first_matching_lines_in(source, pattern, None)
+}
+
+/// whether the start of a source looks like something other than text:
+/// it has a NUL byte, or bytes that can't be UTF-8 however it goes on
+fn is_binary(start: &[u8]) -> bool {
+    if start.contains(&0) {
+        return true;
+    }
+    match str::from_utf8(start) {
+        Ok(_) => false,
+        // `None` means the block just ends partway through a character
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// Like `matching_lines_in`, but stops reading once `limit` lines matched.
+/// The source is read a line at a time, so matches in a pipe are found as
+/// soon as their line arrives. A source whose first block looks binary
+/// doesn't match at all, and past that, lines that aren't UTF-8 are
+/// skipped, so what matches doesn't depend on the limit.
+pub fn first_matching_lines_in<R: Read>(
+    source: R,
+    pattern: &Regex,
+    limit: Option<usize>,
+) -> io::Result<Vec<Match>> {
+    let mut source = BufReader::new(source);
+    if is_binary(source.fill_buf()?) {
+        return Ok(Vec::new());
+    }
+    let mut found = Vec::new();
+    let mut raw = Vec::new();
+    let mut line_number = 0;
+    let mut byte_offset = 0;
+    while limit != Some(found.len()) {
+        raw.clear();
+        if source.read_until(b'\n', &mut raw)? == 0 {
+            break;
+        }
+        line_number += 1;
+        // the same endings `str::lines` drops
+        let line = raw.strip_suffix(b"\n").unwrap_or(&raw);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if let Ok(line) = str::from_utf8(line) {
+            let ranges: Vec<Range<usize>> = pattern
+                .find_iter(line)
+                .map(|m| m.start()..m.end())
+                .collect();
+            if !ranges.is_empty() {
+                found.push(Match {
+                    line_number,
+                    byte_offset,
+                    bytes: line.as_bytes().to_vec(),
+                    ranges,
+                });
+            }
+        }
+        byte_offset += raw.len();
+    }
+    Ok(found)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::_matching_lines;
+    use super::{first_matching_lines_in, matching_lines, matching_lines_in, Match};
     use regex::Regex;
+    use std::io::{self, Read};
+    use std::path::PathBuf;
+
+    fn _matching_lines(contents: &str, pattern: &Regex) -> Vec<Match> {
+        _first_matching_lines(contents, pattern, None)
+    }
+
+    fn _first_matching_lines(contents: &str, pattern: &Regex, limit: Option<usize>) -> Vec<Match> {
+        first_matching_lines_in(contents.as_bytes(), pattern, limit).expect("reading from memory")
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("read past the limit"))
+        }
+    }
+
+    /// the line number and text of each match
+    fn lines(results: &[Match]) -> Vec<(usize, String)> {
+        results
+            .iter()
+            .map(|m| (m.line_number, m.line().into_owned()))
+            .collect()
+    }
 
     #[test]
     fn the_matchline_finds_something_and_gives_line_number() {
@@ -40,10 +144,10 @@ mod tests {
             also nothing great"
             .to_string();
         let to_find = Regex::new("something").unwrap();
-        let results: Vec<(usize, String)> = _matching_lines(&file_to_search, &to_find);
+        let results: Vec<Match> = _matching_lines(&file_to_search, &to_find);
         assert_eq!(results.len(), 1);
 
-        assert_eq!(results[0], (3, "            something".to_string()));
+        assert_eq!(lines(&results)[0], (3, "            something".to_string()));
     }
 
     #[test]
@@ -55,11 +159,93 @@ mod tests {
             junk line"
             .to_string();
         let to_find = Regex::new("thing").unwrap();
-        let results: Vec<(usize, String)> = _matching_lines(&file_to_search, &to_find);
+        let results: Vec<Match> = _matching_lines(&file_to_search, &to_find);
         assert_eq!(results.len(), 2);
 
-        assert_eq!(results[0], (3, "            thing one".to_string()));
-        assert_eq!(results[1], (4, "            thing two".to_string()));
+        assert_eq!(lines(&results)[0], (3, "            thing one".to_string()));
+        assert_eq!(lines(&results)[1], (4, "            thing two".to_string()));
+    }
+
+    #[test]
+    fn matching_lines_from_a_reader() {
+        let source: &[u8] = b"no\nyes\nno\n";
+        let to_find = Regex::new("yes").unwrap();
+        let results: Vec<Match> = matching_lines_in(source, &to_find).unwrap();
+
+        assert_eq!(lines(&results), vec![(2, "yes".to_string())]);
+    }
+
+    #[test]
+    fn matching_lines_reports_files_it_cant_open() {
+        let to_find = Regex::new("thing").unwrap();
+        let missing = PathBuf::from("no/such/file");
+        assert!(matching_lines(&missing, &to_find).is_err());
+    }
+
+    #[test]
+    fn first_matching_lines_stops_at_limit() {
+        let file_to_search = "thing one\nthing two\nthing three";
+        let to_find = Regex::new("thing").unwrap();
+        let results = _first_matching_lines(file_to_search, &to_find, Some(2));
+
+        assert_eq!(
+            lines(&results),
+            vec![(1, "thing one".to_string()), (2, "thing two".to_string())]
+        );
     }
 
+    #[test]
+    fn first_matching_lines_stops_reading_at_limit() {
+        // a reader that fails if read past the first line
+        let source = Read::chain(&b"thing one\n"[..], FailingReader);
+        let to_find = Regex::new("thing").unwrap();
+        let results = first_matching_lines_in(source, &to_find, Some(1)).unwrap();
+
+        assert_eq!(lines(&results), vec![(1, "thing one".to_string())]);
+    }
+
+    #[test]
+    fn binary_sources_do_not_match_with_or_without_a_limit() {
+        let to_find = Regex::new("thing").unwrap();
+        for source in [&b"thing one\n\xff\nthing two\n"[..], b"thing one\0\n"] {
+            assert!(matching_lines_in(source, &to_find).unwrap().is_empty());
+            let first = first_matching_lines_in(source, &to_find, Some(1)).unwrap();
+            assert!(first.is_empty());
+        }
+    }
+
+    #[test]
+    fn later_lines_that_arent_utf8_are_skipped_with_or_without_a_limit() {
+        let mut source = "text\n".repeat(2000).into_bytes();
+        source.extend_from_slice(b"\xff thing\nthing one\nthing two\n");
+        let to_find = Regex::new("thing").unwrap();
+
+        let all = matching_lines_in(&source[..], &to_find).unwrap();
+        assert_eq!(
+            lines(&all),
+            vec![
+                (2002, "thing one".to_string()),
+                (2003, "thing two".to_string())
+            ]
+        );
+        let first = first_matching_lines_in(&source[..], &to_find, Some(1)).unwrap();
+        assert_eq!(lines(&first), vec![(2002, "thing one".to_string())]);
+    }
+
+    #[test]
+    fn matches_know_where_they_are() {
+        let source: &[u8] = b"no\r\nab yes ab\nno\n";
+        let to_find = Regex::new("ab").unwrap();
+        let results = matching_lines_in(source, &to_find).unwrap();
+
+        assert_eq!(
+            results,
+            vec![Match {
+                line_number: 2,
+                byte_offset: 4,
+                bytes: b"ab yes ab".to_vec(),
+                ranges: vec![0..2, 7..9],
+            }]
+        );
+    }
 }
@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+/// The compression formats `-z` knows how to see through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+const FORMATS: [Format; 4] = [Format::Gzip, Format::Bzip2, Format::Xz, Format::Zstd];
+
+/// long enough to hold the longest magic number
+const HEADER_LEN: u64 = 6;
+
+impl Format {
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Format::Gzip => b"\x1f\x8b",
+            Format::Bzip2 => b"BZh",
+            Format::Xz => b"\xfd7zXZ\x00",
+            Format::Zstd => b"\x28\xb5\x2f\xfd",
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Gzip => &["gz", "tgz"],
+            Format::Bzip2 => &["bz2", "tbz2"],
+            Format::Xz => &["xz", "txz"],
+            Format::Zstd => &["zst", "zstd"],
+        }
+    }
+
+    pub fn from_extension(p: &Path) -> Option<Format> {
+        let ext = p.extension()?.to_str()?.to_lowercase();
+        FORMATS
+            .iter()
+            .cloned()
+            .find(|f| f.extensions().contains(&ext.as_str()))
+    }
+
+    pub fn from_magic(header: &[u8]) -> Option<Format> {
+        FORMATS
+            .iter()
+            .cloned()
+            .find(|f| header.starts_with(f.magic()))
+    }
+}
+
+/// work out how the file at `p` is compressed from its name and first few
+/// bytes. The bytes have the last word: a `.gz` that isn't gzip gets
+/// searched as it is, and a rotated `syslog.2` that is gets decompressed.
+pub fn detect(p: &Path, header: &[u8]) -> Option<Format> {
+    match Format::from_extension(p) {
+        Some(f) if header.starts_with(f.magic()) => Some(f),
+        _ => Format::from_magic(header),
+    }
+}
+
+/// open the file at `p` for searching, decompressing it on the fly if it
+/// is in one of the known formats
+pub fn open(p: &Path) -> io::Result<Box<dyn Read>> {
+    let mut f = File::open(p)?;
+    let mut header = Vec::new();
+    (&mut f).take(HEADER_LEN).read_to_end(&mut header)?;
+    let format = detect(p, &header);
+
+    // put back what was read to sniff the format
+    let raw = Cursor::new(header).chain(f);
+    Ok(match format {
+        Some(Format::Gzip) => Box::new(MultiGzDecoder::new(raw)),
+        Some(Format::Bzip2) => Box::new(MultiBzDecoder::new(raw)),
+        Some(Format::Xz) => Box::new(XzDecoder::new_multi_decoder(raw)),
+        Some(Format::Zstd) => Box::new(ZstdDecoder::new(raw)?),
+        None => Box::new(raw),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, Format};
+    use std::path::Path;
+
+    #[test]
+    fn detects_by_magic_bytes() {
+        let p = Path::new("syslog.2");
+        assert_eq!(detect(p, b"\x1f\x8b\x08\x00"), Some(Format::Gzip));
+        assert_eq!(detect(p, b"BZh91AY"), Some(Format::Bzip2));
+        assert_eq!(detect(p, b"\xfd7zXZ\x00"), Some(Format::Xz));
+        assert_eq!(detect(p, b"\x28\xb5\x2f\xfd\x00"), Some(Format::Zstd));
+        assert_eq!(detect(p, b"plain"), None);
+    }
+
+    #[test]
+    fn extension_alone_is_not_enough() {
+        assert_eq!(
+            Format::from_extension(Path::new("a.tar.GZ")),
+            Some(Format::Gzip)
+        );
+        assert_eq!(detect(Path::new("a.tar.gz"), b"plain"), None);
+    }
+}
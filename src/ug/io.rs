@@ -1,146 +1,538 @@
-use getopts::{Matches, Options};
+use std::ops::Range;
 
+use getopts::Options;
+
+use args::Args;
 use core;
+use printer;
 
-enum DisplayMode {
+/// which `Printer` search results get printed with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisplayMode {
+    /// `-l`, just the names of files with matches
     OnlyFiles,
+    /// `-c`, each file with how many lines matched
     CountMatches,
+    #[default]
     Regular,
 }
 
-fn get_display_mode(opts: &Matches) -> DisplayMode {
-    if opts.opt_present("l") {
-        return DisplayMode::OnlyFiles;
-    }
-    if opts.opt_present("c") {
-        return DisplayMode::CountMatches;
-    }
-    DisplayMode::Regular
-}
-
 /// given the matches, generate output as a
 /// stream of lines that will then be printed later
-pub fn display_output(results: Vec<core::FileResult>, opts: &Matches) -> Vec<String> {
+pub fn display_output(results: Vec<core::FileMatches>, args: &Args) -> Vec<String> {
+    let mut printer = printer::for_args(args);
     let mut o: Vec<String> = Vec::new();
-    for (pat, matching_lines) in results {
-        if !matching_lines.is_empty() {
-            match get_display_mode(&opts) {
-                DisplayMode::OnlyFiles => {
-                    o.push(format!("{}", pat.display()));
-                }
-                DisplayMode::CountMatches => {
-                    o.push(format!("{}:{}", pat.display(), matching_lines.len()));
-                }
-                DisplayMode::Regular => {
-                    o.push(format!("{}", pat.display()));
-                    for (line_num, lin) in matching_lines {
-                        o.push(format!("{}:{}", line_num, lin));
-                    }
-                }
-            }
-        }
+    for file in &results {
+        o.extend(printer.file(file));
     }
+    o.extend(printer.finish());
     o
 }
 
-fn print_usage(program: &str, opts: &Options) {
-    let brief = format!("Usage: {} PATTERN [PATH] [options]", program);
-    print!("{}", opts.usage(&brief));
+/// shorten a matching line for display. Lines over `max_columns`
+/// characters are replaced by a notice, and lines over `width` characters
+/// are cut down to a `width` wide window that shows the first match.
+/// `ranges` are where the matches are, as byte offsets into `line`.
+pub fn fit_line(
+    line: &str,
+    ranges: &[Range<usize>],
+    width: Option<usize>,
+    max_columns: Option<usize>,
+) -> String {
+    let len = line.chars().count();
+    if let Some(max) = max_columns {
+        if len > max {
+            return format!("[omitted long line with {} matches]", ranges.len());
+        }
+    }
+    let width = match width {
+        Some(w) if len > w => w,
+        _ => return line.to_string(),
+    };
+
+    // work in characters so multibyte text is never split
+    let offsets: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+    let match_start = match ranges.first() {
+        Some(m) => offsets.iter().position(|&i| i == m.start).unwrap_or(0),
+        None => 0,
+    };
+    // leave a little context before the match when it needs scrolling to
+    let start = if match_start < width {
+        0
+    } else {
+        (match_start - width / 4).min(len - width)
+    };
+    let end = start + width;
+
+    let byte_at = |c: usize| if c < len { offsets[c] } else { line.len() };
+    let mut fitted = String::new();
+    if start > 0 {
+        fitted.push_str("[...]");
+    }
+    fitted.push_str(&line[byte_at(start)..byte_at(end)]);
+    if end < len {
+        fitted.push_str("[...]");
+    }
+    fitted
+}
+
+/// the `--help` text
+pub fn usage(program: &str) -> String {
+    let brief = format!(
+        "Usage: {0} PATTERN [PATH...] [options]\n       <command> | {0} PATTERN [options]\n       {0} --files [PATH...] [options]\n       {0} -g PATTERN [PATH...] [options]",
+        program
+    );
+    opt_parser().usage(&brief)
 }
 
-fn opt_parser() -> Options {
-    let mut opts = Options::new();
-    opts.optflag(
+/// how many values an option takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Takes {
+    Nothing,
+    One,
+    Many,
+}
+
+/// One command line option. The parser, `--help` and the generated
+/// completions are all built from `OPTIONS`, so a new flag only has to be
+/// added there.
+pub struct OptSpec {
+    /// the one letter name, empty if there isn't one
+    pub short: &'static str,
+    /// the long name, empty if there isn't one
+    pub long: &'static str,
+    pub desc: &'static str,
+    /// what the value is called in the help, empty for flags
+    pub hint: &'static str,
+    pub takes: Takes,
+    /// every value the option accepts, when there's a fixed set of them
+    pub values: &'static [&'static str],
+}
+
+const fn flag(short: &'static str, long: &'static str, desc: &'static str) -> OptSpec {
+    OptSpec {
+        short,
+        long,
+        desc,
+        hint: "",
+        takes: Takes::Nothing,
+        values: &[],
+    }
+}
+
+const fn opt(
+    short: &'static str,
+    long: &'static str,
+    desc: &'static str,
+    hint: &'static str,
+) -> OptSpec {
+    OptSpec {
+        short,
+        long,
+        desc,
+        hint,
+        takes: Takes::One,
+        values: &[],
+    }
+}
+
+const fn multi(
+    short: &'static str,
+    long: &'static str,
+    desc: &'static str,
+    hint: &'static str,
+) -> OptSpec {
+    OptSpec {
+        takes: Takes::Many,
+        ..opt(short, long, desc, hint)
+    }
+}
+
+/// what `--sort` and `--sortr` accept
+pub const SORT_KEYS: &[&str] = &["path", "modified", "accessed", "created"];
+
+/// what `--generate-completions` accepts
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// what `--generate` accepts
+pub const GENERATED: &[&str] = &["man"];
+
+/// every option ug understands, in the order `--help` lists them
+pub static OPTIONS: &[OptSpec] = &[
+    flag(
         "l",
         "list-files",
         "List only files that contain the pattern",
-    );
-    opts.optflag(
+    ),
+    flag(
         "c",
         "count",
         "Only print the number of matches in each file",
-    );
+    ),
+    opt(
+        "G",
+        "file-search-regex",
+        "Only search files whose paths match PATTERN",
+        "PATTERN",
+    ),
+    multi(
+        "",
+        "ignore",
+        "Ignore files and directories matching PATTERN",
+        "PATTERN",
+    ),
+    multi(
+        "",
+        "ignore-dir",
+        "Ignore directories matching PATTERN",
+        "PATTERN",
+    ),
+    multi(
+        "",
+        "glob",
+        "Search only files matching GLOB, or skip them if it starts with !. Takes precedence \
+         over ignore rules",
+        "GLOB",
+    ),
+    opt(
+        "",
+        "depth",
+        "Search up to NUM directories deep, -1 for unlimited (default: 25)",
+        "NUM",
+    ),
+    opt("", "max-depth", "Same as --depth", "NUM"),
+    flag(
+        "n",
+        "norecurse",
+        "Only search the top level of each directory",
+    ),
+    flag("", "hidden", "Search hidden files and directories"),
+    flag("f", "follow", "Follow symlinks (default: false)"),
+    flag(
+        "",
+        "one-device",
+        "Don't follow links to other devices or descend into other mounts",
+    ),
+    flag(
+        "z",
+        "search-zip",
+        "Search the contents of gzip, bzip2, xz and zstd compressed files, and of the files \
+         inside tar and zip archives. Files inside archives are not listed by --files or \
+         filtered by --glob, -G or --pre-glob",
+    ),
+    opt(
+        "",
+        "pre",
+        "Search the output of COMMAND run on each file instead of the file itself",
+        "COMMAND",
+    ),
+    multi(
+        "",
+        "pre-glob",
+        "Only run the --pre command on files matching GLOB",
+        "GLOB",
+    ),
+    opt(
+        "m",
+        "max-count",
+        "Stop searching a file after NUM matching lines",
+        "NUM",
+    ),
+    opt(
+        "",
+        "max-results",
+        "Stop searching altogether after NUM matching lines",
+        "NUM",
+    ),
+    opt(
+        "",
+        "max-filesize",
+        "Skip files larger than SIZE bytes, K, M and G suffixes are allowed",
+        "SIZE",
+    ),
+    opt(
+        "W",
+        "width",
+        "Truncate printed lines after NUM characters, keeping the match in view",
+        "NUM",
+    ),
+    opt(
+        "",
+        "max-columns",
+        "Print a notice instead of lines longer than NUM characters",
+        "NUM",
+    ),
+    OptSpec {
+        values: SORT_KEYS,
+        ..opt(
+            "",
+            "sort",
+            "Search files in order of path, modified, accessed or created",
+            "SORTBY",
+        )
+    },
+    OptSpec {
+        values: SORT_KEYS,
+        ..opt("", "sortr", "Like --sort, but in reverse", "SORTBY")
+    },
+    flag(
+        "",
+        "stats",
+        "Print how much was searched and how long it took after the results",
+    ),
+    flag(
+        "",
+        "no-messages",
+        "Don't print errors, or notes about files and directories that were skipped",
+    ),
+    flag("", "no-config", "Ignore the config file"),
+    flag(
+        "",
+        "print-config",
+        "Print the arguments ug runs with once the config file is merged in",
+    ),
+    flag(
+        "",
+        "files",
+        "Print the files that would be searched, without searching them",
+    ),
+    opt(
+        "g",
+        "",
+        "Print the files that would be searched whose paths match PATTERN",
+        "PATTERN",
+    ),
+    OptSpec {
+        values: SHELLS,
+        ..opt(
+            "",
+            "generate-completions",
+            "Print a completion script for SHELL, one of bash, zsh or fish",
+            "SHELL",
+        )
+    },
+    OptSpec {
+        values: GENERATED,
+        ..opt(
+            "",
+            "generate",
+            "Print the man page when KIND is man",
+            "KIND",
+        )
+    },
+    flag("h", "help", "Print this help and exit"),
+    flag("V", "version", "Print the version of ug and exit"),
+];
+
+fn opt_parser() -> Options {
+    let mut opts = Options::new();
+    for o in OPTIONS {
+        match o.takes {
+            Takes::Nothing => opts.optflag(o.short, o.long, o.desc),
+            Takes::One => opts.optopt(o.short, o.long, o.desc, o.hint),
+            Takes::Many => opts.optmulti(o.short, o.long, o.desc, o.hint),
+        };
+    }
     opts
 }
 
-pub fn get_opts(args: &[String]) -> Result<(String, String, Matches), String> {
-    let program = args[0].clone();
+/// parse and check the command line, `args[0]` being the program name
+pub fn get_opts(args: &[String]) -> Result<Args, String> {
+    let matches = opt_parser().parse(&args[1..]).map_err(|f| f.to_string())?;
+    Args::from_matches(&matches)
+}
 
-    let opts = opt_parser();
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(f) => panic!(f.to_string()),
+/// read a size like `512`, `100K`, `10M` or `1G` as a number of bytes
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "invalid size '{}', expected a number with an optional K, M or G",
+            size
+        )
     };
-    match matches.free.clone().as_slice() {
-        [] => {
-            print_usage(&program, &opts);
-            Err("not enough args".to_string())
-        }
-        [pattern] => Ok((pattern.to_string(), ".".to_string(), matches)),
-        [pattern, path] => Ok((pattern.to_string(), path.to_string(), matches)),
-        _ => {
-            print_usage(&program, &opts);
-            Err("too many args".to_string())
-        }
+    let (digits, multiplier) = match size.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    match digits.parse::<u64>() {
+        Ok(n) => n.checked_mul(multiplier).ok_or_else(invalid),
+        Err(_) => Err(invalid()),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{display_output, get_opts};
+    use super::{display_output, fit_line, get_opts, parse_size};
+    use args::Mode;
+    use core::{FileMatches, Match};
+    use regex::Regex;
+    use std::ops::Range;
     use std::path::Path;
 
+    /// `line` shortened around where `pattern` matches it
+    fn fit(line: &str, pattern: &str, width: Option<usize>, max_columns: Option<usize>) -> String {
+        let ranges: Vec<Range<usize>> = Regex::new(pattern)
+            .unwrap()
+            .find_iter(line)
+            .map(|m| m.range())
+            .collect();
+        fit_line(line, &ranges, width, max_columns)
+    }
+
+    /// a match of the whole of `line`
+    fn whole_line(line_number: usize, line: &str) -> Match {
+        Match {
+            line_number,
+            byte_offset: 0,
+            bytes: line.as_bytes().to_vec(),
+            ranges: vec![Range {
+                start: 0,
+                end: line.len(),
+            }],
+        }
+    }
+
     #[test]
     fn test_file_only_printer() {
         let p = Path::new("test_file.txt").to_path_buf();
-        let m = vec![(1, "a match".to_string())];
+        let m = vec![whole_line(1, "a match")];
         let args = vec!["self".to_string(), "beh".to_string(), "-l".to_string()];
         let (_, opts) = match get_opts(&args) {
-            Ok((_, _, o)) => (1, o),
+            Ok(o) => (1, o),
             Err(_) => panic!("at the disco"),
         };
 
-        let file_result = vec![(p, m)];
+        let file_result = vec![FileMatches {
+            path: p,
+            matches: m,
+        }];
         let output = display_output(file_result, &opts);
-        assert_eq!(vec![format!("test_file.txt")], output);
+        assert_eq!(vec!["test_file.txt".to_string()], output);
     }
 
     #[test]
     fn test_regular_search_display() {
         let p = Path::new("test_file.txt").to_path_buf();
-        let m = vec![(1, format!("a match"))];
-        let args = vec![format!("self"), format!("beh")];
+        let m = vec![whole_line(1, "a match")];
+        let args = vec!["self".to_string(), "beh".to_string()];
         let (_, opts) = match get_opts(&args) {
-            Ok((_, _, o)) => (1, o),
+            Ok(o) => (1, o),
             Err(_) => panic!("sure hope not"),
         };
 
-        let file_result = vec![(p, m)];
+        let file_result = vec![FileMatches {
+            path: p,
+            matches: m,
+        }];
         let output = display_output(file_result, &opts);
-        assert_eq!(vec![format!("test_file.txt"), format!("1:a match")], output);
+        assert_eq!(
+            vec!["test_file.txt".to_string(), "1:a match".to_string()],
+            output
+        );
     }
 
     #[test]
     fn test_match_counter() {
         let p1 = Path::new("test_file.txt").to_path_buf();
-        let m1 = vec![(1, "a match".to_string())];
+        let m1 = vec![whole_line(1, "a match")];
 
         let p2 = Path::new("second_file.txt").to_path_buf();
-        let m2 = vec![(1, "a match".to_string()), (2, "and another".to_string())];
+        let m2 = vec![whole_line(1, "a match"), whole_line(2, "and another")];
 
         let args = vec!["self".to_string(), "beh".to_string(), "-c".to_string()];
 
         let (_, opts) = match get_opts(&args) {
-            Ok((_, _, o)) => (1, o),
+            Ok(o) => (1, o),
             Err(_) => panic!("should never happen"),
         };
 
-        let file_result = vec![(p1, m1), (p2, m2)];
+        let file_result = vec![
+            FileMatches {
+                path: p1,
+                matches: m1,
+            },
+            FileMatches {
+                path: p2,
+                matches: m2,
+            },
+        ];
         let output = display_output(file_result, &opts);
         assert_eq!(
-            vec![format!("test_file.txt:1"), format!("second_file.txt:2")],
+            vec![
+                "test_file.txt:1".to_string(),
+                "second_file.txt:2".to_string()
+            ],
             output
         );
     }
 
+    #[test]
+    fn test_listing_files_needs_no_pattern() {
+        let args = vec![
+            "self".to_string(),
+            "--files".to_string(),
+            "src".to_string(),
+            "tests".to_string(),
+        ];
+        let opts = match get_opts(&args) {
+            Ok(parsed) => parsed,
+            Err(_) => panic!("--files should not need a pattern"),
+        };
+        assert!(opts.pattern.is_none());
+        assert_eq!(vec!["src".to_string(), "tests".to_string()], opts.paths);
+        assert_eq!(Mode::ListFiles, opts.mode);
+    }
+
+    #[test]
+    fn test_file_name_search_takes_pattern_from_flag() {
+        let args = vec!["self".to_string(), "-g".to_string(), "beh".to_string()];
+        let opts = match get_opts(&args) {
+            Ok(parsed) => parsed,
+            Err(_) => panic!("-g should not need a free pattern"),
+        };
+        assert!(opts.pattern.is_none());
+        assert!(opts.paths.is_empty());
+        assert_eq!("beh", opts.file_name_pattern.unwrap().as_str());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Ok(512), parse_size("512"));
+        assert_eq!(Ok(100 * 1024), parse_size("100K"));
+        assert_eq!(Ok(10 * 1024 * 1024), parse_size("10m"));
+        assert_eq!(Ok(1024 * 1024 * 1024), parse_size("1G"));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1.5M").is_err());
+        assert!(parse_size("99999999999999G").is_err());
+    }
+
+    #[test]
+    fn test_short_lines_fit() {
+        assert_eq!("abc", fit("abc", "b", Some(3), Some(3)));
+        assert_eq!("abc", fit("abc", "b", None, None));
+    }
+
+    #[test]
+    fn test_width_truncates_after_match() {
+        assert_eq!("abcd[...]", fit("abcdefgh", "b", Some(4), None));
+    }
+
+    #[test]
+    fn test_width_scrolls_to_late_match() {
+        let line = format!("{}needle{}", "x".repeat(30), "y".repeat(30));
+        assert_eq!("[...]xxneedleyy[...]", fit(&line, "needle", Some(10), None));
+        let line = format!("{}needle", "é".repeat(30));
+        assert_eq!("[...]éééneedle", fit(&line, "needle", Some(9), None));
+    }
+
+    #[test]
+    fn test_max_columns_omits_line() {
+        assert_eq!(
+            "[omitted long line with 3 matches]",
+            fit("a-a-a-", "a", Some(2), Some(5))
+        );
+    }
 }
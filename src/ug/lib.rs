@@ -1,7 +1,28 @@
 extern crate regex;
 extern crate getopts;
+extern crate bzip2;
+extern crate flate2;
+extern crate glob;
+extern crate tar;
+extern crate xz2;
+extern crate zip;
+extern crate zstd;
 
 #[macro_use]
 pub mod core;
 #[macro_use]
 pub mod io;
+pub mod args;
+pub mod complete;
+pub mod man;
+pub mod decompress;
+pub mod archive;
+pub mod preprocess;
+pub mod stats;
+pub mod config;
+pub mod printer;
+pub mod search;
+pub mod walk;
+
+#[cfg(test)]
+mod testutil;
@@ -0,0 +1,144 @@
+use io::{Takes, OPTIONS};
+
+const DESCRIPTION: &str = "\
+ug recursively searches PATH, or the current directory, for lines matching \
+the regular expression PATTERN and prints each file with matches followed by \
+its matching lines. When no PATH is given and something is piped in, ug \
+searches its standard input instead.";
+
+const IGNORE_RULES: &str = "\
+Each line of a .gitignore in the directory ug is run from is a glob to skip. \
+A trailing / only matches directories, and a pattern containing a / is matched \
+against the path from that directory rather than just the file name, whichever \
+PATH a file was found through. Blank lines and lines \
+starting with # are left out. The --ignore and --ignore-dir patterns are added \
+to these rules.
+.PP
+Hidden files and directories are skipped unless --hidden is given, even when \
+a --glob matches them, and .git directories are always skipped. Otherwise a \
+--glob takes precedence over the ignore rules, and when any --glob is given \
+without a !, only files matching one are searched.
+.PP
+All of these rules apply to files on disk. When an archive is searched with \
+-z, every file inside it is searched, whatever its name.";
+
+const OUTPUT_FORMATS: &str = "\
+By default every file with matches is printed on its own line, followed by \
+each matching line as LINE:TEXT. With -l only the names of files with \
+matches are printed, and with -c each one is printed as PATH:COUNT. Files \
+inside archives searched with -z are named ARCHIVE:MEMBER, and standard input \
+is named <stdin>. --width and --max-columns shorten long lines, and --stats \
+adds a summary at the end.";
+
+const EXIT_CODES: &str = "\
+.TP
+0
+The search ran, whether or not anything matched.
+.TP
+1
+The arguments, the pattern or a glob were invalid.";
+
+const FILES: &str = "\
+.TP
+~/.config/ug/config
+Flags to always run with, one per line. Flags given on the command line \
+override the same ones from this file. $XDG_CONFIG_HOME/ug/config is \
+used when XDG_CONFIG_HOME is set, and $UG_CONFIG_PATH overrides both.";
+
+/// make text safe to put in a roff document
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+/// the man page for ug, with an entry for every option in `OPTIONS`
+pub fn page() -> String {
+    let mut s = format!(
+        ".TH UG 1 \"\" \"ug {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    s.push_str(".SH NAME\nug \\- recursively search files for a pattern\n");
+    s.push_str(
+        ".SH SYNOPSIS\n\
+         \\fBug\\fR [\\fIOPTIONS\\fR] \\fIPATTERN\\fR [\\fIPATH\\fR...]\n.br\n\
+         \\fIcommand\\fR | \\fBug\\fR [\\fIOPTIONS\\fR] \\fIPATTERN\\fR\n.br\n\
+         \\fBug\\fR \\fB\\-\\-files\\fR [\\fIOPTIONS\\fR] [\\fIPATH\\fR...]\n.br\n\
+         \\fBug\\fR \\fB\\-g\\fR \\fIPATTERN\\fR [\\fIOPTIONS\\fR] [\\fIPATH\\fR...]\n",
+    );
+    s.push_str(&format!(".SH DESCRIPTION\n{}\n", escape_lines(DESCRIPTION)));
+
+    s.push_str(".SH OPTIONS\n");
+    for o in OPTIONS {
+        let mut names = Vec::new();
+        if !o.short.is_empty() {
+            names.push(format!("\\fB\\-{}\\fR", escape(o.short)));
+        }
+        if !o.long.is_empty() {
+            names.push(format!("\\fB\\-\\-{}\\fR", escape(o.long)));
+        }
+        let mut item = names.join(", ");
+        if o.takes != Takes::Nothing {
+            item.push_str(&format!(" \\fI{}\\fR", o.hint));
+        }
+        s.push_str(&format!(".TP\n{}\n{}.\n", item, escape(o.desc)));
+        if o.takes == Takes::Many {
+            s.push_str("Can be given more than once.\n");
+        }
+    }
+
+    s.push_str(&format!(
+        ".SH IGNORE RULES\n{}\n",
+        escape_lines(IGNORE_RULES)
+    ));
+    s.push_str(&format!(
+        ".SH OUTPUT FORMATS\n{}\n",
+        escape_lines(OUTPUT_FORMATS)
+    ));
+    s.push_str(&format!(".SH EXIT CODES\n{}\n", escape_lines(EXIT_CODES)));
+    s.push_str(&format!(".SH FILES\n{}\n", escape_lines(FILES)));
+    s
+}
+
+/// escape each line of text that may hold its own roff requests
+fn escape_lines(text: &str) -> String {
+    text.lines()
+        .map(|l| {
+            if l.starts_with(".TP") || l.starts_with(".PP") {
+                l.to_string()
+            } else {
+                escape(l)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, page};
+    use io::OPTIONS;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("\\-\\-files", escape("--files"));
+        assert_eq!("\\&.gitignore lines", escape(".gitignore lines"));
+        assert_eq!("a\\eb", escape("a\\b"));
+    }
+
+    #[test]
+    fn test_page_has_every_option_and_section() {
+        let page = page();
+        assert!(page.starts_with(".TH UG 1"));
+        for o in OPTIONS.iter().filter(|o| !o.long.is_empty()) {
+            let name = format!("\\fB\\-\\-{}\\fR", o.long.replace('-', "\\-"));
+            assert!(page.contains(&name), "missing {}", o.long);
+        }
+        for section in &["OPTIONS", "IGNORE RULES", "OUTPUT FORMATS", "EXIT CODES"] {
+            assert!(page.contains(&format!(".SH {}\n", section)));
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use glob::Pattern;
+
+/// A user command whose output gets searched instead of the file it is
+/// run on, like `pdftotext` for PDFs.
+pub struct Preprocessor {
+    command: String,
+    /// only files matching one of these are run through the command, all of
+    /// them if there are none
+    globs: Vec<Pattern>,
+}
+
+impl Preprocessor {
+    pub fn new(command: &str, globs: &[String]) -> Result<Preprocessor, String> {
+        let mut patterns = Vec::new();
+        for g in globs {
+            match Pattern::new(g) {
+                Ok(p) => patterns.push(p),
+                Err(e) => return Err(format!("invalid --pre-glob '{}': {}", g, e)),
+            }
+        }
+        Ok(Preprocessor {
+            command: command.to_string(),
+            globs: patterns,
+        })
+    }
+
+    pub fn applies_to(&self, p: &Path) -> bool {
+        if self.globs.is_empty() {
+            return true;
+        }
+        let name = match p.file_name() {
+            Some(name) => Path::new(name),
+            None => return false,
+        };
+        self.globs
+            .iter()
+            .any(|g| g.matches_path(name) || g.matches_path(p))
+    }
+
+    /// run the command with the file's path as its only argument and the
+    /// file on its stdin, and hand back what it printed
+    pub fn run(&self, p: &Path) -> io::Result<Vec<u8>> {
+        let output = Command::new(&self.command)
+            .arg(p)
+            .stdin(File::open(p)?)
+            .stderr(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "preprocessor '{}' failed: {}",
+                self.command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Preprocessor;
+    use std::path::Path;
+
+    #[test]
+    fn applies_to_everything_without_globs() {
+        let pre = Preprocessor::new("pdftotext", &[]).unwrap();
+        assert!(pre.applies_to(Path::new("./a/b.txt")));
+    }
+
+    #[test]
+    fn applies_only_to_matching_globs() {
+        let pre = Preprocessor::new("pdftotext", &["*.pdf".to_string()]).unwrap();
+        assert!(pre.applies_to(Path::new("./docs/manual.pdf")));
+        assert!(!pre.applies_to(Path::new("./docs/manual.txt")));
+    }
+}
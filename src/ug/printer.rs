@@ -0,0 +1,143 @@
+use args::Args;
+use core::FileMatches;
+use io::{fit_line, DisplayMode};
+
+/// Turns what a search found into lines of output, one searched file at a
+/// time. New output formats, and library users who want the results
+/// somewhere else, implement this.
+pub trait Printer {
+    /// the lines to print for one searched file, which might not have
+    /// matched anything
+    fn file(&mut self, file: &FileMatches) -> Vec<String>;
+
+    /// the lines to print once every file has been searched
+    fn finish(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `-l`, just the names of files with matches
+pub struct ListPrinter;
+
+impl Printer for ListPrinter {
+    fn file(&mut self, file: &FileMatches) -> Vec<String> {
+        if file.matches.is_empty() {
+            return Vec::new();
+        }
+        vec![format!("{}", file.path.display())]
+    }
+}
+
+/// `-c`, each file with how many lines matched
+pub struct CountPrinter;
+
+impl Printer for CountPrinter {
+    fn file(&mut self, file: &FileMatches) -> Vec<String> {
+        if file.matches.is_empty() {
+            return Vec::new();
+        }
+        vec![format!("{}:{}", file.path.display(), file.matches.len())]
+    }
+}
+
+/// The default, each file with matches followed by its matching lines,
+/// shortened by `fit_line` when they're too long.
+pub struct RegularPrinter {
+    pub width: Option<usize>,
+    pub max_columns: Option<usize>,
+}
+
+impl Printer for RegularPrinter {
+    fn file(&mut self, file: &FileMatches) -> Vec<String> {
+        if file.matches.is_empty() {
+            return Vec::new();
+        }
+        let mut o = vec![format!("{}", file.path.display())];
+        for m in &file.matches {
+            let line = fit_line(&m.line(), &m.ranges, self.width, self.max_columns);
+            o.push(format!("{}:{}", m.line_number, line));
+        }
+        o
+    }
+}
+
+/// the printer the command line asks for
+pub fn for_args(args: &Args) -> Box<dyn Printer> {
+    match args.display {
+        DisplayMode::OnlyFiles => Box::new(ListPrinter),
+        DisplayMode::CountMatches => Box::new(CountPrinter),
+        DisplayMode::Regular => Box::new(RegularPrinter {
+            width: args.width,
+            max_columns: args.max_columns,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountPrinter, ListPrinter, Printer, RegularPrinter};
+    use core::FileMatches;
+    use testutil::searched;
+
+    #[test]
+    fn files_without_matches_print_nothing() {
+        let nothing = searched("a", "hay\n", "needle");
+        assert!(ListPrinter.file(&nothing).is_empty());
+        assert!(CountPrinter.file(&nothing).is_empty());
+        let mut regular = RegularPrinter {
+            width: None,
+            max_columns: None,
+        };
+        assert!(regular.file(&nothing).is_empty());
+        assert!(regular.finish().is_empty());
+    }
+
+    #[test]
+    fn each_mode_prints_its_own_way() {
+        let found = searched("a", "needle\nhay\nneedle too\n", "needle");
+        assert_eq!(vec!["a"], ListPrinter.file(&found));
+        assert_eq!(vec!["a:2"], CountPrinter.file(&found));
+        let mut regular = RegularPrinter {
+            width: Some(6),
+            max_columns: None,
+        };
+        assert_eq!(vec!["a", "1:needle", "3:needle[...]"], regular.file(&found));
+    }
+
+    #[test]
+    fn printers_can_keep_state() {
+        /// a CSV sink of its own, the way a library user might write one
+        struct Csv {
+            rows: usize,
+        }
+
+        impl Printer for Csv {
+            fn file(&mut self, file: &FileMatches) -> Vec<String> {
+                let mut o = Vec::new();
+                if self.rows == 0 {
+                    o.push("path,line".to_string());
+                }
+                for m in &file.matches {
+                    self.rows += 1;
+                    o.push(format!("{},{}", file.path.display(), m.line_number));
+                }
+                o
+            }
+
+            fn finish(&mut self) -> Vec<String> {
+                vec![format!("{} rows", self.rows)]
+            }
+        }
+
+        let mut csv = Csv { rows: 0 };
+        assert_eq!(
+            vec!["path,line", "a,1"],
+            csv.file(&searched("a", "needle\n", "needle"))
+        );
+        assert_eq!(
+            vec!["b,2"],
+            csv.file(&searched("b", "hay\nneedle\n", "needle"))
+        );
+        assert_eq!(vec!["2 rows"], csv.finish());
+    }
+}
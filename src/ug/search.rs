@@ -0,0 +1,383 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use regex::Regex;
+
+use archive;
+use args::{Args, SortBy};
+use core::{self, FileMatches};
+use decompress;
+use preprocess::Preprocessor;
+use stats::{Counted, Stats};
+use walk::{self, Skipped, Walk, Walker};
+
+/// what a `Searcher` reports as it goes
+#[derive(Debug)]
+pub enum Event {
+    /// a file, archive member or stdin has been searched, along with the
+    /// lines that matched, if any did
+    Searched(FileMatches),
+    /// a path could not be walked or read, or an archive failed partway
+    /// through
+    Error(PathBuf, io::Error),
+    /// a file or directory was left out because of `max_filesize` or
+    /// `max_depth`
+    Skipped(Skipped),
+}
+
+/// the next file the walk finds, passing errors and whatever it skipped on
+/// the way to `on_event`
+fn next_file<F: FnMut(Event)>(walk: &mut Walk, on_event: &mut F) -> Option<PathBuf> {
+    loop {
+        let entry = walk.next();
+        for skipped in walk.take_skipped() {
+            on_event(Event::Skipped(skipped));
+        }
+        match entry? {
+            Ok(p) => return Some(p),
+            Err(e) => on_event(Event::Error(e.path, e.err)),
+        }
+    }
+}
+
+/// Searches files for a pattern the way the `ug` command does, walking
+/// directories and skipping what the ignore rules say to.
+///
+/// ```no_run
+/// extern crate regex;
+/// extern crate ug;
+///
+/// use ug::search::{Event, Searcher};
+/// use ug::walk::Walker;
+///
+/// # fn main() {
+/// let searcher = Searcher::new()
+///     .pattern(regex::Regex::new("fn main").unwrap())
+///     .walker(Walker::new().path("src").ignore("*.min.js"))
+///     .max_count(Some(1));
+/// searcher
+///     .search(|event| {
+///         if let Event::Searched(result) = event {
+///             for m in result.matches {
+///                 println!("{}:{}:{}", result.path.display(), m.line_number, m.line());
+///             }
+///         }
+///     }).unwrap();
+/// # }
+/// ```
+pub struct Searcher {
+    pattern: Option<Regex>,
+    walker: Walker,
+    sort: Option<(SortBy, bool)>,
+    search_zip: bool,
+    pre: Option<Preprocessor>,
+    max_count: Option<usize>,
+    max_results: Option<usize>,
+}
+
+impl Default for Searcher {
+    fn default() -> Searcher {
+        Searcher::new()
+    }
+}
+
+impl Searcher {
+    /// a searcher with ug's defaults, searching the current directory
+    pub fn new() -> Searcher {
+        Searcher {
+            pattern: None,
+            walker: Walker::new(),
+            sort: None,
+            search_zip: false,
+            pre: None,
+            max_count: None,
+            max_results: None,
+        }
+    }
+
+    /// a searcher set up the way the command line asks for
+    pub fn from_args(args: &Args) -> Result<Searcher, String> {
+        let mut searcher = Searcher::new()
+            .walker(Walker::from_args(args))
+            .search_zip(args.search_zip)
+            .max_count(args.max_count)
+            .max_results(args.max_results);
+        searcher.pattern = args.pattern.clone();
+        if let Some(by) = args.sort {
+            searcher = searcher.sort(by, args.sort_reverse);
+        }
+        if let Some(ref command) = args.pre {
+            searcher = searcher.preprocessor(Preprocessor::new(command, &args.pre_globs)?);
+        }
+        Ok(searcher)
+    }
+
+    /// what to look for in each line
+    pub fn pattern(mut self, pattern: Regex) -> Searcher {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// find the files to search with this walker, which has the paths and
+    /// everything about which files get skipped. `Walker::new()`, walking
+    /// the current directory, is used otherwise.
+    pub fn walker(mut self, walker: Walker) -> Searcher {
+        self.walker = walker;
+        self
+    }
+
+    pub fn sort(mut self, by: SortBy, reverse: bool) -> Searcher {
+        self.sort = Some((by, reverse));
+        self
+    }
+
+    /// look inside compressed files and archives, like `-z`
+    pub fn search_zip(mut self, yes: bool) -> Searcher {
+        self.search_zip = yes;
+        self
+    }
+
+    /// search what the preprocessor makes of the files it applies to
+    pub fn preprocessor(mut self, pre: Preprocessor) -> Searcher {
+        self.pre = Some(pre);
+        self
+    }
+
+    /// stop searching a file after this many matching lines
+    pub fn max_count(mut self, n: Option<usize>) -> Searcher {
+        self.max_count = n;
+        self
+    }
+
+    /// stop searching altogether after this many matching lines
+    pub fn max_results(mut self, n: Option<usize>) -> Searcher {
+        self.max_results = n;
+        self
+    }
+
+    /// every file that would be searched, in the order it would be. Paths
+    /// the walk couldn't get through or left out are passed to `on_event`.
+    pub fn files<F: FnMut(Event)>(&self, mut on_event: F) -> Result<Vec<PathBuf>, String> {
+        let mut walk = self.walker.walk()?;
+        let mut files = Vec::new();
+        while let Some(p) = next_file(&mut walk, &mut on_event) {
+            files.push(p);
+        }
+        if let Some((by, reverse)) = self.sort {
+            walk::sort_files(&mut files, by, reverse);
+        }
+        Ok(files)
+    }
+
+    /// search every file, passing what happens to `on_event` in the order
+    /// of `files`, and return the totals
+    pub fn search<F: FnMut(Event)>(&self, mut on_event: F) -> Result<Stats, String> {
+        // sorting needs every file up front, otherwise search them as
+        // the walk finds them
+        if self.sort.is_some() {
+            let files = self.files(&mut on_event)?;
+            return self.search_files(files, on_event);
+        }
+        let mut walk = self.walker.walk()?;
+        self.search_each(|on_event| next_file(&mut walk, on_event), on_event)
+    }
+
+    /// search each of `files` as it is, `-` being stdin, passing what
+    /// happens to `on_event` in the same order, and return the totals
+    pub fn search_files<I, F>(&self, files: I, on_event: F) -> Result<Stats, String>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        F: FnMut(Event),
+    {
+        let mut files = files.into_iter();
+        self.search_each(|_| files.next(), on_event)
+    }
+
+    /// search the files `next_file` hands out one at a time until it runs
+    /// out, letting it pass on to `on_event` whatever it meets on the way
+    fn search_each<N, F>(&self, mut next_file: N, mut on_event: F) -> Result<Stats, String>
+    where
+        N: FnMut(&mut F) -> Option<PathBuf>,
+        F: FnMut(Event),
+    {
+        let started = Instant::now();
+        let re = match self.pattern {
+            Some(ref re) => re,
+            None => return Err("no pattern to search for".to_string()),
+        };
+
+        let mut stats = Stats::new();
+        let bytes_searched = Cell::new(0);
+        // matching lines still allowed by max_results
+        let mut remaining = self.max_results;
+        while remaining != Some(0) {
+            let p = match next_file(&mut on_event) {
+                Some(p) => p,
+                None => break,
+            };
+            let limit = match (self.max_count, remaining) {
+                (Some(c), Some(r)) => Some(c.min(r)),
+                (c, r) => c.or(r),
+            };
+            for event in self.search_one(p, re, &bytes_searched, limit) {
+                let event = match event {
+                    Event::Searched(mut result) => {
+                        if let Some(r) = remaining {
+                            result.matches.truncate(r);
+                            remaining = Some(r - result.matches.len());
+                        }
+                        stats.record(&result);
+                        Event::Searched(result)
+                    }
+                    e => e,
+                };
+                on_event(event);
+            }
+        }
+
+        stats.bytes_searched = bytes_searched.get();
+        stats.elapsed = started.elapsed();
+        Ok(stats)
+    }
+
+    /// search one file. Archives searched with `-z` turn into one result
+    /// per member, everything else into a single result. Files the
+    /// preprocessor applies to get its output searched instead. Each
+    /// result holds at most `limit` matching lines.
+    fn search_one(
+        &self,
+        p: PathBuf,
+        re: &Regex,
+        bytes_searched: &Cell<u64>,
+        limit: Option<usize>,
+    ) -> Vec<Event> {
+        let searched = |path: PathBuf, source: &mut dyn Read| {
+            let before = bytes_searched.get();
+            let source = Counted::new(source, bytes_searched);
+            match core::first_matching_lines_in(source, re, limit) {
+                Ok(matches) => Event::Searched(FileMatches { path, matches }),
+                Err(e) => {
+                    // a file that couldn't be read wasn't searched
+                    bytes_searched.set(before);
+                    Event::Error(path, e)
+                }
+            }
+        };
+
+        if p == Path::new("-") {
+            let stdin = io::stdin();
+            vec![searched(PathBuf::from("<stdin>"), &mut stdin.lock())]
+        } else if let Some(pre) = self.pre.as_ref().filter(|pre| pre.applies_to(&p)) {
+            match pre.run(&p) {
+                Ok(output) => vec![searched(p, &mut &output[..])],
+                Err(e) => vec![Event::Error(p, e)],
+            }
+        } else if self.search_zip && archive::kind(&p).is_some() {
+            let mut events = Vec::new();
+            let walked = archive::for_each_member(&p, |member, contents| {
+                events.push(searched(archive::member_path(&p, member), contents));
+            });
+            if let Err(e) = walked {
+                events.push(Event::Error(p, e));
+            }
+            events
+        } else if self.search_zip {
+            match decompress::open(&p) {
+                Ok(mut source) => vec![searched(p, &mut source)],
+                Err(e) => vec![Event::Error(p, e)],
+            }
+        } else {
+            match File::open(&p) {
+                Ok(mut f) => vec![searched(p, &mut f)],
+                Err(e) => vec![Event::Error(p, e)],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Searcher};
+    use regex::Regex;
+    use std::path::PathBuf;
+    use testutil::TempDir;
+    use walk::Walker;
+
+    fn tree(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        dir.write("a.txt", "needle\nhay\nneedle again\n");
+        dir.write("sub/b.txt", "hay\nneedle\n");
+        dir.write("sub/c.log", "needle\n");
+        dir
+    }
+
+    #[test]
+    fn searches_a_tree() {
+        let dir = tree("tree");
+        let mut found = Vec::new();
+        let stats = Searcher::new()
+            .pattern(Regex::new("needle").unwrap())
+            .walker(Walker::new().path(dir.to_str().unwrap()).ignore("*.log"))
+            .search(|event| {
+                if let Event::Searched(result) = event {
+                    let p = result.path.strip_prefix(&dir).unwrap().to_path_buf();
+                    found.push((p, result.matches.len()));
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            vec![(PathBuf::from("a.txt"), 2), (PathBuf::from("sub/b.txt"), 1)],
+            found
+        );
+        assert_eq!(3, stats.lines_matched);
+        assert_eq!(2, stats.files_searched);
+    }
+
+    #[test]
+    fn searches_the_files_given() {
+        let dir = tree("given");
+        let mut found = Vec::new();
+        Searcher::new()
+            .pattern(Regex::new("needle").unwrap())
+            .search_files(vec![dir.join("sub/c.log"), dir.join("a.txt")], |event| {
+                if let Event::Searched(result) = event {
+                    let p = result.path.strip_prefix(&dir).unwrap().to_path_buf();
+                    found.push((p, result.matches.len()));
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            vec![(PathBuf::from("sub/c.log"), 1), (PathBuf::from("a.txt"), 2)],
+            found
+        );
+    }
+
+    #[test]
+    fn limits_results() {
+        let dir = tree("limits");
+        let mut lines = 0;
+        Searcher::new()
+            .pattern(Regex::new("needle").unwrap())
+            .walker(Walker::new().path(dir.to_str().unwrap()).max_depth(Some(0)))
+            .max_results(Some(1))
+            .search(|event| {
+                if let Event::Searched(result) = event {
+                    lines += result.matches.len();
+                }
+            })
+            .unwrap();
+        assert_eq!(1, lines);
+    }
+
+    #[test]
+    fn needs_a_pattern_and_valid_globs() {
+        assert!(Searcher::new().search(|_| {}).is_err());
+        let bad_glob = Searcher::new()
+            .pattern(Regex::new("x").unwrap())
+            .walker(Walker::new().glob("["));
+        assert!(bad_glob.search(|_| {}).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+
+use core;
+
+/// Totals for a whole search, printed by `--stats`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub lines_matched: usize,
+    pub bytes_searched: u64,
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// count one searched file
+    pub fn record(&mut self, result: &core::FileMatches) {
+        self.files_searched += 1;
+        if !result.matches.is_empty() {
+            self.files_matched += 1;
+            self.lines_matched += result.matches.len();
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} matches", self.lines_matched)?;
+        writeln!(f, "{} files contained matches", self.files_matched)?;
+        writeln!(f, "{} files searched", self.files_searched)?;
+        writeln!(f, "{} bytes searched", self.bytes_searched)?;
+        write!(f, "{:.6} seconds", self.elapsed.as_secs_f64())
+    }
+}
+
+/// A reader that adds up how many bytes went through it, so the
+/// decompressed or preprocessed size is what gets counted.
+pub struct Counted<'a, R> {
+    inner: R,
+    count: &'a Cell<u64>,
+}
+
+impl<'a, R: Read> Counted<'a, R> {
+    pub fn new(inner: R, count: &'a Cell<u64>) -> Counted<'a, R> {
+        Counted { inner, count }
+    }
+}
+
+impl<'a, R: Read> Read for Counted<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counted, Stats};
+    use std::cell::Cell;
+    use std::io::Read;
+    use std::time::Duration;
+    use testutil::searched;
+
+    #[test]
+    fn counts_files_and_lines() {
+        let mut stats = Stats::new();
+        stats.record(&searched("a", "x\n", "x"));
+        stats.record(&searched("b", "y\n", "x"));
+        stats.record(&searched("c", "x\ny\ny\nx\n", "x"));
+
+        assert_eq!(stats.files_searched, 3);
+        assert_eq!(stats.files_matched, 2);
+        assert_eq!(stats.lines_matched, 3);
+    }
+
+    #[test]
+    fn summary() {
+        let stats = Stats {
+            files_searched: 3,
+            files_matched: 2,
+            lines_matched: 5,
+            bytes_searched: 100,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            stats.to_string(),
+            "5 matches\n2 files contained matches\n3 files searched\n\
+             100 bytes searched\n1.500000 seconds"
+        );
+    }
+
+    #[test]
+    fn counted_reader_adds_up_bytes() {
+        let count = Cell::new(10);
+        let mut s = String::new();
+        Counted::new(&b"hello"[..], &count)
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(count.get(), 15);
+    }
+}
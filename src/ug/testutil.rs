@@ -0,0 +1,67 @@
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use regex::Regex;
+
+use core::{matching_lines_in, FileMatches};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh directory for a test to put files in. It is removed when
+/// dropped, so it goes away even when the test panics.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// an empty directory, named after `name` and a counter so tests
+    /// running at the same time never share one
+    pub fn new(name: &str) -> TempDir {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("ug-{}-{}-{}", name, process::id(), id));
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    /// write `contents` to `name` in this directory, creating the
+    /// directories it goes in
+    pub fn write(&self, name: &str, contents: &str) {
+        let p = self.path.join(name);
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(p, contents).unwrap();
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// what searching `contents` for `pattern` finds, as if it were the file
+/// at `path`
+pub fn searched(path: &str, contents: &str, pattern: &str) -> FileMatches {
+    let pattern = Regex::new(pattern).unwrap();
+    FileMatches {
+        path: PathBuf::from(path),
+        matches: matching_lines_in(contents.as_bytes(), &pattern).unwrap(),
+    }
+}
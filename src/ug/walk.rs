@@ -0,0 +1,1019 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+use std::vec;
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+
+use args::{Args, SortBy, DEFAULT_MAX_DEPTH};
+
+/// One exclusion rule, either a line from `.gitignore` or an `--ignore` /
+/// `--ignore-dir` pattern given on the command line.
+struct IgnoreRule {
+    pattern: Pattern,
+    /// only applies to directories, like a gitignore line ending in `/`
+    dir_only: bool,
+    /// matched against the whole path instead of just the file name, like a
+    /// gitignore line containing a `/`
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    fn new(line: &str, dir_only: bool) -> Result<IgnoreRule, String> {
+        let mut line = line;
+        let mut dir_only = dir_only;
+        if line.ends_with('/') {
+            dir_only = true;
+            line = line.trim_end_matches('/');
+        }
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+        match Pattern::new(line) {
+            Ok(pattern) => Ok(IgnoreRule {
+                pattern,
+                dir_only,
+                anchored,
+            }),
+            Err(e) => Err(format!("invalid ignore pattern '{}': {}", line, e)),
+        }
+    }
+
+    /// `from_root` is `p` as a path from the directory anchored rules
+    /// start at, `None` when `p` is outside of it
+    fn matches(&self, p: &Path, from_root: Option<&Path>, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            let anchored_match = MatchOptions {
+                require_literal_separator: true,
+                ..MatchOptions::new()
+            };
+            match from_root {
+                Some(from_root) => self.pattern.matches_path_with(from_root, &anchored_match),
+                None => false,
+            }
+        } else {
+            match p.file_name() {
+                Some(name) => self.pattern.matches_path(Path::new(name)),
+                None => false,
+            }
+        }
+    }
+}
+
+/// `--glob` entries. Unlike ignore rules these can also whitelist a path.
+struct Override {
+    pattern: Pattern,
+    exclude: bool,
+}
+
+impl Override {
+    fn new(glob: &str) -> Result<Override, String> {
+        let (exclude, glob) = match glob.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, glob),
+        };
+        match Pattern::new(glob) {
+            Ok(pattern) => Ok(Override { pattern, exclude }),
+            Err(e) => Err(format!("invalid glob '{}': {}", glob, e)),
+        }
+    }
+
+    fn matches(&self, p: &Path) -> bool {
+        let by_name = match p.file_name() {
+            Some(name) => self.pattern.matches_path(Path::new(name)),
+            None => false,
+        };
+        by_name || self.pattern.matches_path(&relative(p))
+    }
+}
+
+/// Everything that decides whether a path met while walking gets searched.
+///
+/// Checks run in this order:
+///
+/// 1. Paths that always get skipped, like `.git` directories.
+/// 2. `-G` narrows down files (never directories) to those whose path
+///    matches the regex.
+/// 3. Hidden files and directories unless `--hidden` is given, even when
+///    a glob would match them.
+/// 4. `--glob` overrides have the final say when one matches: `!GLOB`
+///    excludes, a plain `GLOB` includes even if something below would
+///    ignore it. Once any plain glob is given, files matching none of them
+///    are skipped.
+/// 5. `--ignore` / `--ignore-dir` patterns and `.gitignore` entries.
+struct Filters {
+    /// where ignore patterns containing a `/` start from, the canonical
+    /// current directory, which is also where `.gitignore` is read from
+    root: Option<PathBuf>,
+    always_ignored: Vec<IgnoreRule>,
+    file_search_regex: Option<Regex>,
+    overrides: Vec<Override>,
+    ignore_rules: Vec<IgnoreRule>,
+    /// search dotfiles and descend into dot directories
+    hidden: bool,
+    /// files larger than this many bytes are skipped
+    max_filesize: Option<u64>,
+}
+
+impl Filters {
+    fn allows(&self, p: &Path, is_dir: bool) -> bool {
+        let from_root = self.path_from_root(p);
+        let from_root = from_root.as_deref();
+        if self
+            .always_ignored
+            .iter()
+            .any(|r| r.matches(p, from_root, is_dir))
+        {
+            return false;
+        }
+
+        if !is_dir {
+            if let Some(ref re) = self.file_search_regex {
+                if !re.is_match(&relative(p).to_string_lossy()) {
+                    return false;
+                }
+            }
+        }
+
+        if !self.hidden && is_hidden(p) {
+            return false;
+        }
+
+        let mut has_includes = false;
+        for o in self.overrides.iter().rev() {
+            if o.matches(p) {
+                return !o.exclude;
+            }
+            has_includes = has_includes || !o.exclude;
+        }
+        if has_includes && !is_dir {
+            return false;
+        }
+        !self
+            .ignore_rules
+            .iter()
+            .any(|r| r.matches(p, from_root, is_dir))
+    }
+
+    /// `p` as a path from `root`. Relative paths without `..` already are
+    /// one, anything else has to be resolved first, which is only worth a
+    /// syscall when some rule is anchored.
+    fn path_from_root(&self, p: &Path) -> Option<PathBuf> {
+        let plain = p
+            .components()
+            .all(|c| matches!(c, Component::CurDir | Component::Normal(_)));
+        if plain {
+            return Some(relative(p));
+        }
+        let root = self.root.as_ref()?;
+        let mut rules = self.always_ignored.iter().chain(&self.ignore_rules);
+        if !rules.any(|r| r.anchored) {
+            return None;
+        }
+        let real = fs::canonicalize(p).ok()?;
+        real.strip_prefix(root).ok().map(Path::to_path_buf)
+    }
+
+    /// whether a file of `len` bytes is within `--max-filesize`
+    fn small_enough(&self, len: u64) -> bool {
+        self.max_filesize.is_none_or(|max| len <= max)
+    }
+}
+
+fn is_hidden(p: &Path) -> bool {
+    match p.file_name() {
+        Some(name) => name.to_string_lossy().starts_with('.'),
+        None => false,
+    }
+}
+
+/// the path with any leading `./` dropped, which is what
+/// patterns get matched against
+pub fn relative(p: &Path) -> PathBuf {
+    p.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+/// whether one of the paths to walk is inside, or the same as, another, so
+/// the same file could be found through both
+fn overlapping(paths: &[String]) -> bool {
+    let real: Vec<PathBuf> = paths
+        .iter()
+        .filter(|p| *p != "-")
+        .filter_map(|p| fs::canonicalize(p).ok())
+        .collect();
+    real.iter().enumerate().any(|(i, a)| {
+        real[i + 1..]
+            .iter()
+            .any(|b| a.starts_with(b) || b.starts_with(a))
+    })
+}
+
+/// How far and through what a `Walk` is allowed to go, as opposed to what
+/// it should skip.
+struct WalkOptions {
+    /// directories below this many levels under a search path are not
+    /// entered, `None` means no limit
+    max_depth: Option<usize>,
+    /// descend into symlinked directories and search symlinked files,
+    /// instead of skipping them
+    follow_links: bool,
+    /// never leave the filesystem a search path is on
+    one_device: bool,
+    /// devices not worth crossing into even without `one_device`
+    pseudo_devices: HashSet<u64>,
+}
+
+/// filesystems that only expose kernel state, searching them is slow at
+/// best and hangs at worst
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "debugfs",
+    "tracefs",
+    "securityfs",
+    "pstore",
+    "bpf",
+    "configfs",
+    "fusectl",
+    "mqueue",
+    "hugetlbfs",
+    "binfmt_misc",
+    "efivarfs",
+    "selinuxfs",
+    "rpc_pipefs",
+    "nsfs",
+];
+
+/// device ids of everything mounted from one of `PSEUDO_FILESYSTEMS`
+#[cfg(target_os = "linux")]
+fn get_pseudo_devices() -> HashSet<u64> {
+    // without the mount table nothing is known to be a pseudo-filesystem
+    lines_of("/proc/self/mounts")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            if !PSEUDO_FILESYSTEMS.contains(&fs_type) {
+                return None;
+            }
+            // spaces and such in mount points are written as octal escapes
+            let mount_point = mount_point
+                .replace("\\040", " ")
+                .replace("\\011", "\t")
+                .replace("\\012", "\n")
+                .replace("\\134", "\\");
+            let m = fs::metadata(mount_point).ok()?;
+            file_id(&m).map(|(dev, _)| dev)
+        }).collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_pseudo_devices() -> HashSet<u64> {
+    HashSet::new()
+}
+
+/// identifies a file or directory no matter which path, symlinked or not,
+/// it was reached through
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(m: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_m: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// Finds the files to search under a set of paths, skipping whatever the
+/// ignore rules and filtering options say to.
+///
+/// Files named directly are kept as they are, `-` stands for stdin, and a
+/// file reachable from several of the paths is only found the first time.
+/// Without any paths the current directory is walked.
+pub struct Walker {
+    paths: Vec<String>,
+    file_search_regex: Option<Regex>,
+    globs: Vec<String>,
+    ignores: Vec<String>,
+    ignore_dirs: Vec<String>,
+    gitignore: bool,
+    hidden: bool,
+    max_filesize: Option<u64>,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    one_device: bool,
+}
+
+impl Default for Walker {
+    fn default() -> Walker {
+        Walker::new()
+    }
+}
+
+impl Walker {
+    /// a walker with ug's defaults
+    pub fn new() -> Walker {
+        Walker {
+            paths: Vec::new(),
+            file_search_regex: None,
+            globs: Vec::new(),
+            ignores: Vec::new(),
+            ignore_dirs: Vec::new(),
+            gitignore: true,
+            hidden: false,
+            max_filesize: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            follow_links: false,
+            one_device: false,
+        }
+    }
+
+    /// a walker set up the way the command line asks for
+    pub fn from_args(args: &Args) -> Walker {
+        Walker {
+            paths: args.paths.clone(),
+            file_search_regex: args.file_search_regex.clone(),
+            globs: args.globs.clone(),
+            ignores: args.ignores.clone(),
+            ignore_dirs: args.ignore_dirs.clone(),
+            gitignore: true,
+            hidden: args.hidden,
+            max_filesize: args.max_filesize,
+            max_depth: args.max_depth,
+            follow_links: args.follow_links,
+            one_device: args.one_device,
+        }
+    }
+
+    /// walk this file or directory, `-` being stdin
+    pub fn path(mut self, path: &str) -> Walker {
+        self.paths.push(path.to_string());
+        self
+    }
+
+    pub fn paths(mut self, paths: &[String]) -> Walker {
+        self.paths.extend_from_slice(paths);
+        self
+    }
+
+    /// only find files whose paths match, like `-G`
+    pub fn file_search_regex(mut self, re: Regex) -> Walker {
+        self.file_search_regex = Some(re);
+        self
+    }
+
+    /// only find files matching the glob, or skip them if it starts
+    /// with `!`, like `--glob`
+    pub fn glob(mut self, glob: &str) -> Walker {
+        self.globs.push(glob.to_string());
+        self
+    }
+
+    /// skip files and directories matching the glob, like `--ignore`
+    pub fn ignore(mut self, pattern: &str) -> Walker {
+        self.ignores.push(pattern.to_string());
+        self
+    }
+
+    /// skip directories matching the glob, like `--ignore-dir`
+    pub fn ignore_dir(mut self, pattern: &str) -> Walker {
+        self.ignore_dirs.push(pattern.to_string());
+        self
+    }
+
+    /// whether to skip what `.gitignore` in the current directory lists,
+    /// on by default
+    pub fn gitignore(mut self, yes: bool) -> Walker {
+        self.gitignore = yes;
+        self
+    }
+
+    pub fn hidden(mut self, yes: bool) -> Walker {
+        self.hidden = yes;
+        self
+    }
+
+    pub fn max_filesize(mut self, bytes: Option<u64>) -> Walker {
+        self.max_filesize = bytes;
+        self
+    }
+
+    /// how many directories deep to go, `None` for no limit
+    pub fn max_depth(mut self, depth: Option<usize>) -> Walker {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn follow_links(mut self, yes: bool) -> Walker {
+        self.follow_links = yes;
+        self
+    }
+
+    pub fn one_device(mut self, yes: bool) -> Walker {
+        self.one_device = yes;
+        self
+    }
+
+    /// combine what the repo says to ignore with the filtering options
+    fn filters(&self) -> Result<Filters, String> {
+        let overrides = self
+            .globs
+            .iter()
+            .map(|g| Override::new(g))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut ignore_rules = Vec::new();
+        for pattern in &self.ignores {
+            ignore_rules.push(IgnoreRule::new(pattern, false)?);
+        }
+        for pattern in &self.ignore_dirs {
+            ignore_rules.push(IgnoreRule::new(pattern, true)?);
+        }
+        if self.gitignore {
+            ignore_rules.extend(get_ignored_files_from_config()?);
+        }
+
+        Ok(Filters {
+            root: env::current_dir().and_then(fs::canonicalize).ok(),
+            file_search_regex: self.file_search_regex.clone(),
+            overrides,
+            always_ignored: get_things_you_should_ignore(),
+            ignore_rules,
+            hidden: self.hidden,
+            max_filesize: self.max_filesize,
+        })
+    }
+
+    /// start walking, failing if an ignore pattern or glob is invalid
+    pub fn walk(&self) -> Result<Walk, String> {
+        let roots = if self.paths.is_empty() {
+            vec![".".to_string()]
+        } else {
+            self.paths.clone()
+        };
+        // telling files apart costs memory for every one of them, so only
+        // do it when the same file could really turn up twice
+        let seen = if overlapping(&roots) {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        Ok(Walk {
+            roots: roots.into_iter(),
+            stack: Vec::new(),
+            seen,
+            skipped: Vec::new(),
+            filters: self.filters()?,
+            options: WalkOptions {
+                max_depth: self.max_depth,
+                follow_links: self.follow_links,
+                one_device: self.one_device,
+                pseudo_devices: get_pseudo_devices(),
+            },
+        })
+    }
+}
+
+/// A path the walk couldn't get through, and why. The walk carries on past
+/// it.
+#[derive(Debug)]
+pub struct WalkError {
+    pub path: PathBuf,
+    pub err: io::Error,
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.err)
+    }
+}
+
+/// Something the walk left out because of a limit rather than an ignore
+/// rule, which the user may want to be told about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Skipped {
+    /// a file over `max_filesize`, and how many bytes it is
+    TooLarge(PathBuf, u64),
+    /// a directory deeper than `max_depth`
+    TooDeep(PathBuf),
+}
+
+/// a directory partway through being walked
+struct Dir {
+    /// what's left to look at in it, next one last
+    entries: Vec<PathBuf>,
+    /// entries that couldn't be read, handed out before the rest
+    errors: Vec<WalkError>,
+    id: Option<FileId>,
+}
+
+/// The files a `Walker` finds, in walk order, found as they are asked for.
+///
+/// Directories still being walked are kept on a stack rather than the call
+/// stack, so however deep a tree goes the walk only holds one directory's
+/// listing per level.
+pub struct Walk {
+    roots: vec::IntoIter<String>,
+    /// the directories being walked, innermost last
+    stack: Vec<Dir>,
+    /// every file handed out so far, only kept when the paths being walked
+    /// overlap
+    seen: Option<HashSet<FileId>>,
+    /// what has been left out since `take_skipped` was last called
+    skipped: Vec<Skipped>,
+    filters: Filters,
+    options: WalkOptions,
+}
+
+impl Walk {
+    /// Skip the rest of the directory the last file came from, along with
+    /// everything under it not walked yet. The walk carries on in the
+    /// directory above.
+    pub fn skip_current_dir(&mut self) {
+        self.stack.pop();
+    }
+
+    /// What the walk has left out because of `max_filesize` or `max_depth`
+    /// since this was last called. Nothing is printed about them, so it's
+    /// up to the caller whether the user hears about it.
+    pub fn take_skipped(&mut self) -> Vec<Skipped> {
+        mem::take(&mut self.skipped)
+    }
+
+    /// a file to hand out if it's small enough to search and hasn't been
+    /// handed out already
+    fn file(&mut self, p: PathBuf, m: &fs::Metadata) -> Option<Result<PathBuf, WalkError>> {
+        if !self.filters.small_enough(m.len()) {
+            self.skipped.push(Skipped::TooLarge(p, m.len()));
+            return None;
+        }
+        if let (Some(seen), Some(id)) = (self.seen.as_mut(), file_id(m)) {
+            if !seen.insert(id) {
+                return None;
+            }
+        }
+        Some(Ok(p))
+    }
+
+    /// start on the directory at `p`. The stack holds every directory
+    /// between the search root and `p`, so a symlink pointing back up the
+    /// tree can be caught instead of being followed forever.
+    fn enter(&mut self, p: &Path, m: &fs::Metadata) -> Result<(), WalkError> {
+        let id = file_id(m);
+        if id.is_some() && self.stack.iter().any(|d| d.id == id) {
+            return Err(WalkError {
+                path: p.to_path_buf(),
+                err: io::Error::other("symlink loop detected, not following it"),
+            });
+        }
+        // read_dir order depends on the filesystem, sort so every machine
+        // walks the same tree the same way
+        let listing = fs::read_dir(p).map_err(|err| WalkError {
+            path: p.to_path_buf(),
+            err,
+        })?;
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for entry in listing {
+            match entry {
+                Ok(entry) => entries.push(entry.path()),
+                Err(err) => errors.push(WalkError {
+                    path: p.to_path_buf(),
+                    err,
+                }),
+            }
+        }
+        entries.sort();
+        entries.reverse();
+        self.stack.push(Dir {
+            entries,
+            errors,
+            id,
+        });
+        Ok(())
+    }
+
+    /// one of the paths the walk was given, a file to hand out if it is one
+    fn start(&mut self, path: &str) -> Option<Result<PathBuf, WalkError>> {
+        let root = Path::new(path);
+        if path == "-" {
+            return Some(Ok(root.to_path_buf()));
+        }
+        match fs::metadata(root) {
+            Ok(ref m) if m.is_dir() => self.enter(root, m).err().map(Err),
+            Ok(ref m) => self.file(root.to_path_buf(), m),
+            Err(err) => Some(Err(WalkError {
+                path: root.to_path_buf(),
+                err,
+            })),
+        }
+    }
+
+    /// something found in the innermost directory, a file to hand out if
+    /// it is one that should be searched
+    fn visit(&mut self, p: PathBuf) -> Option<Result<PathBuf, WalkError>> {
+        let metadata = if self.options.follow_links {
+            fs::metadata(&p)
+        } else {
+            fs::symlink_metadata(&p)
+        };
+        // a dangling symlink, or something deleted while we were looking
+        let metadata = match metadata {
+            Ok(m) => m,
+            Err(err) => return Some(Err(WalkError { path: p, err })),
+        };
+        let is_dir = metadata.is_dir();
+        if !self.filters.allows(&p, is_dir) {
+            return None;
+        }
+        if is_dir {
+            let depth = self.stack.len() - 1;
+            if self.options.max_depth.is_some_and(|max| depth >= max) {
+                self.skipped.push(Skipped::TooDeep(p));
+                return None;
+            }
+            if let (Some((dev, _)), Some((root_dev, _))) = (file_id(&metadata), self.stack[0].id) {
+                let other_device = dev != root_dev;
+                if other_device
+                    && (self.options.one_device || self.options.pseudo_devices.contains(&dev))
+                {
+                    return None;
+                }
+            }
+            self.enter(&p, &metadata).err().map(Err)
+        } else if metadata.is_file() {
+            self.file(p, &metadata)
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, WalkError>;
+
+    fn next(&mut self) -> Option<Result<PathBuf, WalkError>> {
+        loop {
+            if let Some(e) = self.stack.last_mut().and_then(|dir| dir.errors.pop()) {
+                return Some(Err(e));
+            }
+            let next_entry = self.stack.last_mut().map(|dir| dir.entries.pop());
+            let found = match next_entry {
+                Some(Some(p)) => self.visit(p),
+                Some(None) => {
+                    self.stack.pop();
+                    continue;
+                }
+                None => match self.roots.next() {
+                    Some(path) => self.start(&path),
+                    None => return None,
+                },
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+}
+
+/// the lines of a file, none if there is no such file. Bytes that aren't
+/// UTF-8 are replaced rather than refusing the whole file.
+fn lines_of(file: &str) -> io::Result<Vec<String>> {
+    match File::open(file) {
+        Ok(mut f) => {
+            let mut buffer = Vec::new();
+            f.read_to_end(&mut buffer)?;
+            let text = String::from_utf8_lossy(&buffer);
+            Ok(text.lines().map(ToOwned::to_owned).collect())
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn get_ignored_files_from_config() -> Result<Vec<IgnoreRule>, String> {
+    lines_of(".gitignore")
+        .map_err(|e| format!("could not read .gitignore: {}", e))?
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| IgnoreRule::new(line, false))
+        .collect()
+}
+
+/// version control internals, skipped at any depth no matter what
+fn get_things_you_should_ignore() -> Vec<IgnoreRule> {
+    vec![IgnoreRule::new(".git/", false).unwrap()]
+}
+
+/// put the whole list of files in order. The sort is stable, so files the
+/// key can't tell apart, or whose times can't be read, stay in walk order.
+pub fn sort_files(files: &mut [PathBuf], by: SortBy, reverse: bool) {
+    match by {
+        SortBy::Path => files.sort(),
+        _ => {
+            let time_of = |p: &PathBuf| {
+                let m = fs::metadata(p).ok()?;
+                match by {
+                    SortBy::Modified => m.modified().ok(),
+                    SortBy::Accessed => m.accessed().ok(),
+                    _ => m.created().ok(),
+                }
+            };
+            files.sort_by_cached_key(time_of);
+        }
+    }
+    if reverse {
+        files.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Skipped, Walker};
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use testutil::TempDir;
+
+    /// a small tree to walk in a fresh temporary directory
+    fn tree(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        for f in &[
+            "b.txt",
+            "a.log",
+            ".dotfile",
+            "sub/c.txt",
+            "sub/deeper/d.txt",
+            ".hidden/e.txt",
+            ".git/config",
+            "node_modules/f.js",
+        ] {
+            dir.write(f, "contents\n");
+        }
+        dir
+    }
+
+    /// walk `dir` and give back what was found relative to it
+    fn found(dir: &Path, walker: Walker) -> Vec<String> {
+        let walker = walker.gitignore(false).path(dir.to_str().unwrap());
+        walker
+            .walk()
+            .unwrap()
+            .map(|p| {
+                let p = p.unwrap();
+                p.strip_prefix(dir).unwrap().to_string_lossy().into_owned()
+            }).collect()
+    }
+
+    #[test]
+    fn walks_in_order_skipping_hidden_and_git() {
+        let dir = tree("order");
+        assert_eq!(
+            vec![
+                "a.log",
+                "b.txt",
+                "node_modules/f.js",
+                "sub/c.txt",
+                "sub/deeper/d.txt"
+            ],
+            found(&dir, Walker::new())
+        );
+        assert_eq!(
+            vec![
+                ".dotfile",
+                ".hidden/e.txt",
+                "a.log",
+                "b.txt",
+                "node_modules/f.js",
+                "sub/c.txt",
+                "sub/deeper/d.txt",
+            ],
+            found(&dir, Walker::new().hidden(true))
+        );
+    }
+
+    #[test]
+    fn ignores_and_globs() {
+        let dir = tree("ignores");
+        assert_eq!(
+            vec!["b.txt", "sub/c.txt", "sub/deeper/d.txt"],
+            found(
+                &dir,
+                Walker::new().ignore("*.log").ignore_dir("node_modules")
+            )
+        );
+        assert_eq!(
+            vec!["node_modules/f.js"],
+            found(&dir, Walker::new().glob("*.js"))
+        );
+        assert_eq!(
+            vec!["a.log", "b.txt", "node_modules/f.js", "sub/c.txt"],
+            found(&dir, Walker::new().glob("!d.txt"))
+        );
+    }
+
+    #[test]
+    fn globs_dont_find_hidden_files() {
+        let dir = tree("hidden-globs");
+        fs::write(dir.join(".secret.txt"), "contents\n").unwrap();
+        assert_eq!(
+            vec!["b.txt", "sub/c.txt", "sub/deeper/d.txt"],
+            found(&dir, Walker::new().glob("*.txt"))
+        );
+        assert_eq!(
+            vec![
+                ".hidden/e.txt",
+                ".secret.txt",
+                "b.txt",
+                "sub/c.txt",
+                "sub/deeper/d.txt"
+            ],
+            found(&dir, Walker::new().glob("*.txt").hidden(true))
+        );
+    }
+
+    #[test]
+    fn stops_at_max_depth() {
+        let dir = tree("depth");
+        assert_eq!(
+            vec!["a.log", "b.txt"],
+            found(&dir, Walker::new().max_depth(Some(0)))
+        );
+        assert_eq!(
+            vec!["a.log", "b.txt", "node_modules/f.js", "sub/c.txt"],
+            found(&dir, Walker::new().max_depth(Some(1)))
+        );
+    }
+
+    #[test]
+    fn keeps_track_of_what_limits_left_out() {
+        let dir = tree("limits");
+        let mut walk = Walker::new()
+            .gitignore(false)
+            .max_filesize(Some(3))
+            .max_depth(Some(0))
+            .path(dir.to_str().unwrap())
+            .walk()
+            .unwrap();
+        assert!(walk.next().is_none());
+        assert_eq!(
+            vec![
+                Skipped::TooLarge(dir.join("a.log"), 9),
+                Skipped::TooLarge(dir.join("b.txt"), 9),
+                Skipped::TooDeep(dir.join("node_modules")),
+                Skipped::TooDeep(dir.join("sub")),
+            ],
+            walk.take_skipped()
+        );
+        assert!(walk.take_skipped().is_empty());
+    }
+
+    #[test]
+    fn finds_each_file_once() {
+        let dir = tree("once");
+        let sub = dir.join("sub");
+        let walker = Walker::new().path(sub.to_str().unwrap());
+        assert_eq!(
+            vec![
+                "sub/c.txt",
+                "sub/deeper/d.txt",
+                "a.log",
+                "b.txt",
+                "node_modules/f.js"
+            ],
+            found(&dir, walker)
+        );
+    }
+
+    #[test]
+    fn only_keeps_track_of_files_when_paths_overlap() {
+        let dir = tree("overlap");
+        let sub = dir.join("sub");
+        let separate = Walker::new()
+            .path(sub.to_str().unwrap())
+            .path(dir.join("b.txt").to_str().unwrap());
+        assert!(separate.walk().unwrap().seen.is_none());
+        let overlapping = separate.path(dir.to_str().unwrap());
+        assert!(overlapping.walk().unwrap().seen.is_some());
+    }
+
+    #[test]
+    fn walks_lazily() {
+        let dir = tree("lazily");
+        let later = dir.join("later");
+        let walker = Walker::new()
+            .path(dir.join("b.txt").to_str().unwrap())
+            .path(later.to_str().unwrap());
+        let mut walk = walker.walk().unwrap().map(Result::unwrap);
+        assert_eq!(Some(dir.join("b.txt")), walk.next());
+        // made after the walk started, but before it got to it
+        fs::create_dir(&later).unwrap();
+        fs::write(later.join("g.txt"), "").unwrap();
+        assert_eq!(Some(later.join("g.txt")), walk.next());
+        assert_eq!(None, walk.next());
+    }
+
+    #[test]
+    fn prunes_the_current_dir() {
+        let dir = tree("prune");
+        fs::write(dir.join("sub/zz.txt"), "").unwrap();
+        let mut walk = Walker::new()
+            .gitignore(false)
+            .path(dir.to_str().unwrap())
+            .walk()
+            .unwrap();
+        let mut found = Vec::new();
+        while let Some(p) = walk.next() {
+            let p = p.unwrap();
+            if p.ends_with("sub/c.txt") {
+                // drops sub/deeper and sub/zz.txt, but not what follows sub
+                walk.skip_current_dir();
+            }
+            found.push(p.strip_prefix(&dir).unwrap().to_path_buf());
+        }
+        let expected: Vec<PathBuf> = vec!["a.log", "b.txt", "node_modules/f.js", "sub/c.txt"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn reports_errors_and_carries_on() {
+        let dir = tree("errors");
+        let missing = dir.join("missing");
+        let mut walk = Walker::new()
+            .gitignore(false)
+            .path(missing.to_str().unwrap())
+            .path(dir.join("b.txt").to_str().unwrap())
+            .walk()
+            .unwrap();
+        let err = walk.next().unwrap().unwrap_err();
+        assert_eq!(missing, err.path);
+        assert_eq!(io::ErrorKind::NotFound, err.err.kind());
+        assert_eq!(dir.join("b.txt"), walk.next().unwrap().unwrap());
+        assert!(walk.next().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_symlink_loops() {
+        let dir = tree("loops");
+        std::os::unix::fs::symlink(&dir, dir.join("sub/back")).unwrap();
+        let walker = Walker::new()
+            .gitignore(false)
+            .follow_links(true)
+            .path(dir.to_str().unwrap());
+        let errors: Vec<PathBuf> = walker
+            .walk()
+            .unwrap()
+            .filter_map(|entry| entry.err())
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(vec![dir.join("sub/back")], errors);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_dangling_symlinks_when_following() {
+        let dir = tree("dangling");
+        std::os::unix::fs::symlink(dir.join("gone"), dir.join("sub/dangling")).unwrap();
+        let walker = Walker::new().gitignore(false).path(dir.to_str().unwrap());
+        assert!(walker.walk().unwrap().all(|entry| entry.is_ok()));
+        let errors: Vec<(PathBuf, io::ErrorKind)> = walker
+            .follow_links(true)
+            .walk()
+            .unwrap()
+            .filter_map(|entry| entry.err())
+            .map(|e| (e.path, e.err.kind()))
+            .collect();
+        assert_eq!(
+            vec![(dir.join("sub/dangling"), io::ErrorKind::NotFound)],
+            errors
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_are_errors() {
+        assert!(Walker::new().glob("[").walk().is_err());
+        assert!(Walker::new().ignore("a[").walk().is_err());
+    }
+}
@@ -7,6 +7,10 @@ https://github.com/BurntSushi/ripgrep/blob/2913fc4cd063f4d869f54497a313aafbf5330
 
 #![allow(dead_code, unused_imports)]
 
+extern crate flate2;
+extern crate tar;
+
+use std::io::Write;
 use std::process::Command;
 
 use workdir::WorkDir;
@@ -107,3 +111,751 @@ fn test_missing_gitignore() {
     let lines: String = wd.stdout(&mut cmd);
     assert_eq!(lines, "");
 }
+
+clean!(gitignore_that_isnt_utf8, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_bytes(".gitignore", b"caf\xe9\nignore_me\n");
+    wd.create("ignore_me", "test");
+    wd.create("foo", "test");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n1:test\n");
+});
+
+#[test]
+fn anchored_gitignore_lines_apply_to_absolute_paths() {
+    let wd = WorkDir::new("anchored_gitignore_lines_apply_to_absolute_paths");
+    wd.create(".gitignore", "/src/gen.rs\nbuild/out\n");
+    wd.create_dir("src");
+    wd.create("src/gen.rs", "test");
+    wd.create("src/lib.rs", "test");
+    wd.create_dir("build");
+    wd.create("build/out", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("--files").arg(wd.path());
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, format!("{}\n", wd.path().join("src/lib.rs").display()));
+}
+
+clean!(file_search_regex, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("foo.rs", "test");
+    wd.create("foo.py", "test");
+    cmd.arg("-G").arg(r"\.rs$");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo.rs\n1:test\n");
+});
+
+clean!(ignore_pattern_from_cli, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir("sub");
+    wd.create("sub/bundle.min.js", "test");
+    wd.create("foo", "test");
+    cmd.arg("--ignore").arg("*.min.js");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n1:test\n");
+});
+
+clean!(ignore_dir_from_cli, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir("vendor");
+    wd.create("vendor/foo", "test");
+    wd.create("vendor_notes", "test");
+    cmd.arg("--ignore-dir").arg("vendor");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./vendor_notes\n1:test\n");
+});
+
+clean!(ignore_nested_by_git_ignore, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create(".gitignore", "# build output\ntarget/\n*.log\n");
+    wd.create_dir("sub/target");
+    wd.create("sub/target/foo", "test");
+    wd.create("sub/run.log", "test");
+    wd.create("foo", "test");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n1:test\n");
+});
+
+clean!(glob_excludes, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("app.min.js", "test");
+    wd.create("app.js", "test");
+    cmd.arg("--glob").arg("!*.min.js");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./app.js\n1:test\n");
+});
+
+clean!(glob_includes_only_matching, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir("src");
+    wd.create("src/lib.rs", "test");
+    wd.create("notes.txt", "test");
+    cmd.arg("--glob").arg("*.rs");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./src/lib.rs\n1:test\n");
+});
+
+clean!(glob_overrides_gitignore, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create(".gitignore", "generated.rs");
+    wd.create("generated.rs", "test");
+    cmd.arg("--glob").arg("generated.rs");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./generated.rs\n1:test\n");
+});
+
+#[test]
+fn list_files_without_pattern() {
+    let wd = WorkDir::new("list_files_without_pattern");
+    wd.create(".gitignore", "ignore_me");
+    wd.create("ignore_me", "");
+    wd.create_dir("sub");
+    wd.create("sub/bar", "");
+    wd.create("foo", "");
+
+    let mut cmd = wd.command();
+    cmd.arg("--files");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(sort_lines(&lines), "./foo\n./sub/bar\n");
+}
+
+#[test]
+fn list_files_matching_name() {
+    let wd = WorkDir::new("list_files_matching_name");
+    wd.create(".gitignore", "");
+    wd.create_dir("src");
+    wd.create("src/main.rs", "");
+    wd.create("src/notes.md", "");
+
+    let mut cmd = wd.command();
+    cmd.arg("-g").arg(r"\.rs$").arg("src");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "src/main.rs\n");
+}
+
+#[test]
+fn search_several_paths() {
+    let wd = WorkDir::new("search_several_paths");
+    wd.create(".gitignore", "");
+    wd.create_dir("src");
+    wd.create_dir("tests");
+    wd.create_dir("benches");
+    wd.create("src/lib.rs", "test");
+    wd.create("tests/it.rs", "test");
+    wd.create("benches/b.rs", "test");
+    wd.create("top.rs", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("src").arg("tests").arg("top.rs");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "src/lib.rs\n1:test\ntests/it.rs\n1:test\ntop.rs\n1:test\n");
+}
+
+#[test]
+fn overlapping_paths_are_searched_once() {
+    let wd = WorkDir::new("overlapping_paths_are_searched_once");
+    wd.create(".gitignore", "");
+    wd.create_dir("src/inner");
+    wd.create("src/inner/lib.rs", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("src").arg("src/inner").arg("src/inner/lib.rs");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "src/inner/lib.rs\n1:test\n");
+}
+
+#[test]
+fn search_piped_stdin() {
+    let wd = WorkDir::new("search_piped_stdin");
+    wd.create(".gitignore", "");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test");
+    let output = wd.pipe(&mut cmd, "one\ntest two\nthree\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "<stdin>\n2:test two\n");
+}
+
+#[test]
+fn dash_means_stdin() {
+    let wd = WorkDir::new("dash_means_stdin");
+    wd.create(".gitignore", "");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("-").arg("foo");
+    let output = wd.pipe(&mut cmd, "test\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "<stdin>\n1:test\nfoo\n1:test\n"
+    );
+}
+
+#[test]
+fn norecurse_stays_at_top_level() {
+    let wd = WorkDir::new("norecurse_stays_at_top_level");
+    wd.create(".gitignore", "");
+    wd.create_dir("sub");
+    wd.create("sub/foo", "test");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("-n");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./foo\n1:test\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn depth_limits_recursion() {
+    let wd = WorkDir::new("depth_limits_recursion");
+    wd.create(".gitignore", "");
+    wd.create_dir("a/b/c");
+    wd.create("a/foo", "test");
+    wd.create("a/b/foo", "test");
+    wd.create("a/b/c/foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("--depth").arg("1");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./a/foo\n1:test\n");
+    // the limit was asked for, so there's nothing to warn about
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn default_depth_limit_is_noted() {
+    let wd = WorkDir::new("default_depth_limit_is_noted");
+    wd.create(".gitignore", "");
+    let deep = vec!["d"; 30].join("/");
+    wd.create_dir(&deep);
+    wd.create(format!("{}/foo", deep), "test");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./foo\n1:test\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("deeper than 25 levels"));
+}
+
+#[test]
+fn negative_depth_is_unlimited() {
+    let wd = WorkDir::new("negative_depth_is_unlimited");
+    wd.create(".gitignore", "");
+    let deep = "d/".repeat(30);
+    wd.create_dir(&deep);
+    wd.create(format!("{}foo", deep), "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("-g").arg("foo").arg("--depth=-1");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, format!("./{}foo\n", deep));
+
+    let mut cmd = wd.command();
+    cmd.arg("-g").arg("foo");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "");
+}
+
+#[test]
+fn symlinks_skipped_by_default() {
+    let wd = WorkDir::new("symlinks_skipped_by_default");
+    wd.create(".gitignore", "");
+    wd.create_dir("real");
+    wd.create("real/foo", "test");
+    wd.link_dir("real", "linked");
+    wd.link_file("real/foo", "linked_foo");
+
+    let mut cmd = wd.command();
+    cmd.arg("test");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./real/foo\n1:test\n");
+}
+
+#[test]
+fn follow_symlinks() {
+    let wd = WorkDir::new("follow_symlinks");
+    wd.create(".gitignore", "");
+    wd.create_dir("real");
+    wd.create("real/foo", "test");
+    wd.link_dir("real", "linked");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("-f").arg("linked");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "linked/foo\n1:test\n");
+}
+
+#[test]
+fn follow_symlink_loop_is_reported() {
+    let wd = WorkDir::new("follow_symlink_loop_is_reported");
+    wd.create(".gitignore", "");
+    wd.create_dir("a/b");
+    wd.create("a/b/foo", "test");
+    wd.link_dir("a", "a/b/up");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("--follow").arg("a");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a/b/foo\n1:test\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("symlink loop"));
+}
+
+#[test]
+fn one_device_searches_same_filesystem() {
+    let wd = WorkDir::new("one_device_searches_same_filesystem");
+    wd.create(".gitignore", "");
+    wd.create_dir("sub");
+    wd.create("sub/foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("--one-device");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./sub/foo\n1:test\n");
+}
+
+clean!(hidden_skipped_by_default, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir(".config");
+    wd.create(".config/foo", "test");
+    wd.create(".env", "test");
+    wd.create("foo", "test");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n1:test\n");
+});
+
+clean!(search_hidden, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir(".config");
+    wd.create(".config/foo", "test");
+    cmd.arg("--hidden");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./.config/foo\n1:test\n");
+});
+
+clean!(ignore_nested_gitdir, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_dir("vendor/lib/.git");
+    wd.create("vendor/lib/.git/HEAD", "test");
+    wd.create("vendor/lib/foo", "test");
+    cmd.arg("--hidden");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./vendor/lib/foo\n1:test\n");
+});
+
+fn gzipped(contents: &str) -> Vec<u8> {
+    let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    e.write_all(contents.as_bytes()).unwrap();
+    e.finish().unwrap()
+}
+
+clean!(search_zip, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_bytes("app.log.gz", &gzipped("one\ntest two\n"));
+    wd.create_bytes("syslog.2", &gzipped("test three\n"));
+    cmd.arg("-z");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(
+        sort_lines(&lines),
+        sort_lines("./app.log.gz\n2:test two\n./syslog.2\n1:test three\n")
+    );
+});
+
+clean!(compressed_skipped_without_z, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create_bytes("app.log.gz", &gzipped("test\n"));
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "");
+});
+
+clean!(corrupt_compressed_files_are_reported, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    let mut corrupt = gzipped("test\n");
+    let len = corrupt.len();
+    corrupt.truncate(len / 2);
+    wd.create_bytes("bad.gz", &corrupt);
+    wd.create("good", "test\n");
+    cmd.arg("-z").arg("--stats");
+
+    let output = wd.output(&mut cmd);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("./good\n1:test\n"));
+    assert!(stdout.contains("\n1 files searched\n"));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("ug: ./bad.gz: "));
+});
+
+clean!(search_tarball_members, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    let mut builder = tar::Builder::new(Vec::new());
+    for &(name, contents) in &[("inner/path.rs", "a\ntest\n"), ("other.rs", "nothing\n")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents.as_bytes()).unwrap();
+    }
+    let tarball = builder.into_inner().unwrap();
+    let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    e.write_all(&tarball).unwrap();
+    wd.create_bytes("archive.tar.gz", &e.finish().unwrap());
+    cmd.arg("-z").arg("-c");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./archive.tar.gz:inner/path.rs:1\n");
+});
+
+#[cfg(unix)]
+fn create_script(wd: &WorkDir, name: &str, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    wd.create(name, &format!("#!/bin/sh\n{}\n", body));
+    let path = wd.path().join(name);
+    let mut perms = std::fs::metadata(&path).unwrap().permissions();
+    perms.set_mode(0o755);
+    std::fs::set_permissions(&path, perms).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn preprocessor_output_is_searched() {
+    let wd = WorkDir::new("preprocessor_output_is_searched");
+    wd.create(".gitignore", "upper.sh");
+    create_script(&wd, "upper.sh", "tr a-z A-Z");
+    wd.create("doc.pdf", "test\n");
+    wd.create("notes.txt", "test\n");
+
+    let mut cmd = wd.command();
+    cmd.arg("TEST")
+        .arg("--pre")
+        .arg(wd.path().join("upper.sh"))
+        .arg("--pre-glob")
+        .arg("*.pdf");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./doc.pdf\n1:TEST\n");
+}
+
+clean!(stats_after_results, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("foo", "test\ntest\n");
+    wd.create("bar", "nothing\n");
+    cmd.arg("--stats");
+
+    let lines: String = wd.stdout(&mut cmd);
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(
+        &lines[..7],
+        &[
+            "./foo",
+            "1:test",
+            "2:test",
+            "2 matches",
+            "1 files contained matches",
+            "2 files searched",
+            "18 bytes searched",
+        ]
+    );
+    assert!(lines[7].ends_with(" seconds"));
+});
+
+clean!(max_count_per_file, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("foo", "test 1\ntest 2\ntest 3\n");
+    cmd.arg("-m").arg("2");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n1:test 1\n2:test 2\n");
+});
+
+#[test]
+fn max_results_across_files() {
+    let wd = WorkDir::new("max_results_across_files");
+    wd.create(".gitignore", "");
+    wd.create("a", "test 1\ntest 2\n");
+    wd.create("b", "test 3\ntest 4\n");
+    wd.create("c", "test 5\n");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("a").arg("b").arg("c").arg("--max-results").arg("3");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "a\n1:test 1\n2:test 2\nb\n1:test 3\n");
+}
+
+#[test]
+fn max_count_stops_reading_a_pipe() {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    let wd = WorkDir::new("max_count_stops_reading_a_pipe");
+    wd.create(".gitignore", "");
+    let mut cmd = wd.command();
+    cmd.arg("-m").arg("1").arg("test");
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+    let mut child = cmd.spawn().unwrap();
+
+    // the pipe is left open, so ug only finishes if it stops reading early
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "test one").unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).unwrap();
+        tx.send(out).unwrap();
+    });
+    let out = rx.recv_timeout(Duration::from_secs(10));
+    let _ = child.kill();
+    child.wait().unwrap();
+    drop(stdin);
+    assert_eq!(out, Ok("<stdin>\n1:test one\n".to_string()));
+}
+
+clean!(max_filesize_skips_big_files, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("small", "test\n");
+    wd.create("bundle.js", &"test\n".repeat(1000));
+    cmd.arg("--max-filesize").arg("1K");
+
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./small\n1:test\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bundle.js"));
+});
+
+clean!(no_messages_quiets_notes, "test", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("small", "test\n");
+    wd.create("bundle.js", &"test\n".repeat(1000));
+    wd.create_dir("a/b");
+    wd.create("a/b/deep", "test\n");
+    cmd.arg("--max-filesize").arg("1K").arg("--depth").arg("1").arg("--no-messages");
+
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./small\n1:test\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+});
+
+clean!(width_truncates_long_lines, "needle", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("bundle.min.js", &format!("{}needle{}\n", "x".repeat(100), "y".repeat(100)));
+    cmd.arg("-W").arg("20");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./bundle.min.js\n1:[...]xxxxxneedleyyyyyyyyy[...]\n");
+});
+
+clean!(max_columns_omits_long_lines, "needle", ".", |wd: WorkDir, mut cmd: Command| {
+    wd.create("bundle.min.js", &format!("needle{}needle\nneedle\n", "x".repeat(100)));
+    cmd.arg("--max-columns").arg("50");
+
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(
+        lines,
+        "./bundle.min.js\n1:[omitted long line with 2 matches]\n2:needle\n"
+    );
+});
+
+#[test]
+fn walk_order_is_sorted() {
+    let wd = WorkDir::new("walk_order_is_sorted");
+    wd.create(".gitignore", "");
+    for name in &["c", "a", "d", "b"] {
+        wd.create_dir(name);
+        wd.create(format!("{}/x", name), "");
+        wd.create(format!("{}y", name), "");
+    }
+
+    let mut cmd = wd.command();
+    cmd.arg("--files");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(
+        lines,
+        "./a/x\n./ay\n./b/x\n./by\n./c/x\n./cy\n./d/x\n./dy\n"
+    );
+}
+
+#[test]
+fn sort_by_path_across_search_paths() {
+    let wd = WorkDir::new("sort_by_path_across_search_paths");
+    wd.create(".gitignore", "");
+    wd.create("b", "test");
+    wd.create("a", "test");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("b").arg("a").arg("-c").arg("--sort").arg("path");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "a:1\nb:1\n");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("a").arg("b").arg("-c").arg("--sortr").arg("path");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "b:1\na:1\n");
+}
+
+#[test]
+fn sort_by_modified() {
+    let wd = WorkDir::new("sort_by_modified");
+    wd.create(".gitignore", "");
+    wd.create("new", "test");
+    wd.create("old", "test");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(wd.path().join("old"))
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("-c").arg("--sort").arg("modified");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./old:1\n./new:1\n");
+}
+
+#[test]
+fn config_file_flags_are_used() {
+    let wd = WorkDir::new("config_file_flags_are_used");
+    wd.create(".gitignore", "");
+    wd.create(".ugrc", "# list only\n-l\n--ignore\nskip_me\n");
+    wd.create("skip_me", "test");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join(".ugrc")).arg("test");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./foo\n");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join(".ugrc"))
+        .arg("test")
+        .arg("foo")
+        .arg("--no-config");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "foo\n1:test\n");
+}
+
+#[test]
+fn command_line_overrides_config_file() {
+    let wd = WorkDir::new("command_line_overrides_config_file");
+    wd.create(".gitignore", "");
+    wd.create(".ugrc", "--max-count=2\n--hidden\n");
+    wd.create(".foo", "test 1\ntest 2\n");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join(".ugrc"))
+        .arg("test")
+        .arg("-m")
+        .arg("1")
+        .arg("--hidden");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "./.foo\n1:test 1\n");
+}
+
+#[test]
+fn print_merged_config() {
+    let wd = WorkDir::new("print_merged_config");
+    wd.create(".ugrc", "--hidden\n");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join(".ugrc"))
+        .arg("--print-config")
+        .arg("-l");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(
+        lines,
+        format!("# config file: {}\n--hidden\n-l\n", wd.path().join(".ugrc").display())
+    );
+}
+
+#[test]
+fn missing_config_file_is_reported() {
+    let wd = WorkDir::new("missing_config_file_is_reported");
+    wd.create(".gitignore", "");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join("nope")).arg("test");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./foo\n1:test\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config file"));
+}
+
+#[test]
+fn no_config_doesnt_read_the_config_file() {
+    let wd = WorkDir::new("no_config_doesnt_read_the_config_file");
+    wd.create(".gitignore", "");
+    wd.create("foo", "test");
+
+    let mut cmd = wd.command();
+    cmd.env("UG_CONFIG_PATH", wd.path().join("nope"))
+        .arg("--no-config")
+        .arg("test");
+    let output = wd.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "./foo\n1:test\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn print_config_without_a_config_file() {
+    let wd = WorkDir::new("print_config_without_a_config_file");
+
+    let mut cmd = wd.command();
+    cmd.arg("--print-config").arg("-l");
+    let lines: String = wd.stdout(&mut cmd);
+    assert_eq!(lines, "-l\n");
+}
+
+#[test]
+fn help_and_version() {
+    let wd = WorkDir::new("help_and_version");
+
+    let mut cmd = wd.command();
+    cmd.arg("--help");
+    let help: String = wd.stdout(&mut cmd);
+    assert!(help.starts_with("Usage: "));
+    assert!(help.contains("--max-count"));
+
+    let mut cmd = wd.command();
+    cmd.arg("--version");
+    let version: String = wd.stdout(&mut cmd);
+    assert_eq!(version, format!("ug {}\n", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn bad_flags_are_reported_without_panicking() {
+    let wd = WorkDir::new("bad_flags_are_reported_without_panicking");
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("--no-such-flag");
+    wd.assert_err(&mut cmd);
+    let output = cmd.output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no-such-flag"));
+    assert!(stderr.contains("--help"));
+    assert!(!stderr.contains("panicked"));
+
+    let mut cmd = wd.command();
+    cmd.arg("test").arg("--depth").arg("deep");
+    wd.assert_err(&mut cmd);
+}
+
+#[test]
+fn generate_completions() {
+    let wd = WorkDir::new("generate_completions");
+
+    let mut cmd = wd.command();
+    cmd.arg("--generate-completions").arg("fish");
+    let script: String = wd.stdout(&mut cmd);
+    assert!(script.contains("complete -c ug -s m -l max-count -x"));
+
+    let mut cmd = wd.command();
+    cmd.arg("--generate-completions").arg("powershell");
+    wd.assert_err(&mut cmd);
+}
+
+#[test]
+fn generate_man_page() {
+    let wd = WorkDir::new("generate_man_page");
+
+    let mut cmd = wd.command();
+    cmd.arg("--generate").arg("man");
+    let page: String = wd.stdout(&mut cmd);
+    assert!(page.starts_with(".TH UG 1"));
+    assert!(page.contains("\\fB\\-m\\fR, \\fB\\-\\-max\\-count\\fR \\fINUM\\fR\n"));
+    assert!(page.contains(".SH EXIT CODES\n"));
+}
@@ -38,12 +38,12 @@ use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 use std::time::Duration;
 
-static TEST_DIR: &'static str = "the-integration-tests";
-static NEXT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+static TEST_DIR: &str = "the-integration-tests";
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
 
 /// `WorkDir` represents a directory in which tests are run.
 ///
@@ -68,11 +68,11 @@ impl WorkDir {
             .parent()
             .expect("executable's directory")
             .to_path_buf();
-        let dir = root.join(TEST_DIR).join(name).join(&format!("{}", id));
+        let dir = root.join(TEST_DIR).join(name).join(format!("{}", id));
         nice_err(&dir, repeat(|| fs::create_dir_all(&dir)));
         WorkDir {
-            root: root,
-            dir: dir,
+            root,
+            dir,
         }
     }
 
@@ -127,8 +127,10 @@ impl WorkDir {
     /// Creates a new command that is set to use the ripgrep executable in
     /// this working directory.
     pub fn command(&self) -> process::Command {
-        let mut cmd = process::Command::new(&self.bin());
-        cmd.env_remove("RIPGREP_CONFIG_PATH");
+        let mut cmd = process::Command::new(self.bin());
+        // keep whoever runs the tests from having their config picked up
+        cmd.env_remove("UG_CONFIG_PATH");
+        cmd.env("XDG_CONFIG_HOME", &self.dir);
         cmd.current_dir(&self.dir);
         cmd
     }